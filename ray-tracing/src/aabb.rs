@@ -2,7 +2,7 @@ use std::mem;
 
 use crate::ray::{Point, Ray};
 
-#[derive(Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Aabb {
     minimum: Point,
     maximum: Point,
@@ -48,6 +48,17 @@ impl Aabb {
         true
     }
 
+    /// The center of the box.
+    pub fn centroid(&self) -> Point {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    /// The surface area of the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.maximum - self.minimum;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
     pub fn surrounding_box(box0: &Self, box1: &Self) -> Self {
         let calc = |b0: &Point, b1: &Point, func: &dyn Fn(f64, f64) -> f64| {
             let mut a = [0.0; 3];
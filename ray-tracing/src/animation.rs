@@ -0,0 +1,431 @@
+use anyhow::{bail, ensure};
+
+use crate::{
+    aabb::Aabb,
+    description::{Describer, KeyframeDescription, ObjectDescription},
+    hittable::{HitRecord, Hittable},
+    ray::{Point, Ray, Vec3},
+    transform::{Quat, Transform},
+};
+
+/// The time at which animated lights are sampled. Lights are sampled without
+/// a time, the rays testing them start at time zero like [`Ray::new`].
+const LIGHT_TIME: f64 = 0.0;
+
+/// The largest rotation between two samples of the bounding box, smaller
+/// steps give tighter boxes.
+const MAX_STEP_ANGLE: f64 = std::f64::consts::PI / 16.0;
+
+/// The placement of an object at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    time: f64,
+    translation: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+}
+
+impl Keyframe {
+    /// The object is scaled first, then rotated and then translated.
+    pub fn new(time: f64, translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// A keyframe only moving the object.
+    pub fn with_translation(time: f64, translation: Vec3) -> Self {
+        Self::new(time, translation, Quat::identity(), Vec3::ones())
+    }
+
+    /// Get the keyframe's time.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::scaling(self.scale)
+            .then(&Transform::from_quat(&self.rotation))
+            .then(&Transform::translation(self.translation))
+    }
+
+    /// Interpolates the translation and the scale linearly and the rotation
+    /// with a slerp.
+    fn lerp(&self, next: &Self, time: f64) -> Self {
+        let t = (time - self.time) / (next.time - self.time);
+        Self {
+            time,
+            translation: (1.0 - t) * self.translation + t * next.translation,
+            rotation: self.rotation.slerp(&next.rotation, t),
+            scale: (1.0 - t) * self.scale + t * next.scale,
+        }
+    }
+}
+
+/// A transformation changing over time, given by keyframes.
+///
+/// Before the first and after the last keyframe the transformation doesn't
+/// change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    /// Panics if the keyframes are invalid, see [`AnimatedTransform::try_new`].
+    pub fn new(keyframes: Vec<Keyframe>) -> Self {
+        Self::try_new(keyframes).expect("invalid keyframes")
+    }
+
+    /// Fails without any keyframes, if a keyframe scales by zero or if a
+    /// component of the scale changes its sign between two keyframes, as the
+    /// interpolated transformation would flatten the object on the way.
+    pub fn try_new(mut keyframes: Vec<Keyframe>) -> anyhow::Result<Self> {
+        ensure!(
+            !keyframes.is_empty(),
+            "an animation needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| f64::total_cmp(&a.time, &b.time));
+
+        let scale = |k: &Keyframe| <[f64; 3]>::from(k.scale);
+        if let Some(k) = keyframes.iter().find(|k| scale(k).contains(&0.0)) {
+            bail!("the keyframe at {} scales by zero", k.time);
+        }
+        let flips = keyframes.windows(2).find(|w| {
+            let (a, b) = (scale(&w[0]), scale(&w[1]));
+            (0..3).any(|i| a[i].signum() != b[i].signum())
+        });
+        if let Some(w) = flips {
+            bail!(
+                "the scale changes its sign between the keyframes at {} and {}",
+                w[0].time,
+                w[1].time
+            );
+        }
+
+        Ok(Self { keyframes })
+    }
+
+    /// Get a reference to the animation's keyframes, sorted by time.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The interpolated keyframe at the given time.
+    fn keyframe(&self, time: f64) -> Keyframe {
+        let frames = &self.keyframes;
+        let next = frames.partition_point(|k| k.time <= time);
+
+        match next {
+            0 => frames[0],
+            n if n == frames.len() => frames[n - 1],
+            n => frames[n - 1].lerp(&frames[n], time),
+        }
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        self.keyframe(time).transform()
+    }
+
+    /// A box containing `bbox` for every transformation between `time0` and
+    /// `time1`.
+    ///
+    /// The interval is cut into steps rotating at most [`MAX_STEP_ANGLE`].
+    /// Within a step the rotation moves a point at distance `r` from the
+    /// origin by at most `r` times the step angle, so the boxes at the ends of
+    /// every step are grown by twice that amount.
+    pub fn bounding_box(&self, bbox: &Aabb, time0: f64, time1: f64) -> Aabb {
+        // the times where the motion changes
+        let mut times = vec![time0];
+        times.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| time0 < t && t < time1),
+        );
+        times.push(time1);
+
+        let mut output: Option<Aabb> = None;
+        for span in times.windows(2) {
+            let (start, end) = (self.keyframe(span[0]), self.keyframe(span[1]));
+
+            let angle = start.rotation.angle(&end.rotation);
+            let steps = (angle / MAX_STEP_ANGLE).ceil().max(1.0) as usize;
+            let step_angle = angle / steps as f64;
+
+            let mut prev = start;
+            for step in 1..=steps {
+                let next = if step == steps {
+                    end
+                } else {
+                    start.lerp(
+                        &end,
+                        start.time + (end.time - start.time) * step as f64 / steps as f64,
+                    )
+                };
+
+                let bbox = Self::step_box(bbox, &prev, &next, step_angle);
+                output = Some(match output {
+                    Some(output) => Aabb::surrounding_box(&output, &bbox),
+                    None => bbox,
+                });
+
+                prev = next;
+            }
+        }
+
+        output.expect("there is always at least one step")
+    }
+
+    /// The box of a step, the sum of the box of the translations and of the
+    /// grown box of the scaled and rotated object.
+    fn step_box(bbox: &Aabb, a: &Keyframe, b: &Keyframe, angle: f64) -> Aabb {
+        let rotated = |k: &Keyframe| {
+            Transform::scaling(k.scale)
+                .then(&Transform::from_quat(&k.rotation))
+                .transform_box(bbox)
+        };
+        let radius = |k: &Keyframe| {
+            // the distance of the farthest corner
+            let scaled = Transform::scaling(k.scale).transform_box(bbox);
+            (0..3)
+                .map(|axis| {
+                    let (min, max) = (scaled.min().data()[axis], scaled.max().data()[axis]);
+                    f64::max(min * min, max * max)
+                })
+                .sum::<f64>()
+                .sqrt()
+        };
+
+        let grow = 2.0 * angle * f64::max(radius(a), radius(b));
+        let rotated = Aabb::surrounding_box(&rotated(a), &rotated(b));
+
+        let mut min = [0.0; 3];
+        let mut max = [0.0; 3];
+        for axis in 0..3 {
+            let (ta, tb) = (a.translation.data()[axis], b.translation.data()[axis]);
+            min[axis] = f64::min(ta, tb) + rotated.min().data()[axis] - grow;
+            max[axis] = f64::max(ta, tb) + rotated.max().data()[axis] + grow;
+        }
+
+        Aabb::new(min.into(), max.into())
+    }
+}
+
+/// An object moving, rotating and scaling over time.
+///
+/// The transformation is picked by the time of the ray, which gives motion
+/// blur for any kind of object.
+pub struct Animated<H> {
+    ptr: H,
+    animation: AnimatedTransform,
+}
+
+impl<H: Hittable> Animated<H> {
+    pub fn new(ptr: H, animation: AnimatedTransform) -> Self {
+        Self { ptr, animation }
+    }
+
+    /// Get a reference to the object's animation.
+    pub fn animation(&self) -> &AnimatedTransform {
+        &self.animation
+    }
+}
+
+impl<H> Hittable for Animated<H>
+where
+    H: Hittable,
+{
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = self.ptr.bounding_box(time0, time1)?;
+        Some(self.animation.bounding_box(&bbox, time0, time1))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let transform = self.animation.at(r.time());
+        let local = transform.inverse().transform_ray(r);
+
+        let mut rec = self.ptr.hit(&local, t_min, t_max)?;
+        transform.transform_record(&mut rec);
+
+        Some(rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let keyframes = self
+            .animation
+            .keyframes()
+            .iter()
+            .map(|k| KeyframeDescription {
+                time: k.time,
+                translation: k.translation.into(),
+                rotation: k.rotation.into(),
+                scale: k.scale.into(),
+            })
+            .collect();
+
+        Ok(ObjectDescription::Animated {
+            keyframes,
+            object: Box::new(self.ptr.describe(d)?),
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.ptr.is_light()
+    }
+
+    /// Samples the light where it is at [`LIGHT_TIME`]. Hits at other times
+    /// are still found by sampling the materials, which keeps the estimate
+    /// unbiased.
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let transform = self.animation.at(LIGHT_TIME);
+        transform.pdf_value(&self.ptr, origin, direction)
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        self.animation.at(LIGHT_TIME).random(&self.ptr, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        hittable::HittableList,
+        material::{DiffuseLight, Lambertian},
+        objects::{rect, Cube},
+        rand_range,
+    };
+
+    #[test]
+    fn test_interpolation() {
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::with_translation(1.0, [0.0, 2.0, 0.0].into()),
+            Keyframe::new(
+                0.0,
+                Vec3::zeros(),
+                Quat::from_axis_angle([0.0, 0.0, 1.0].into(), 90.0),
+                [2.0, 2.0, 2.0].into(),
+            ),
+        ]);
+
+        let p = Point::new(1.0, 0.0, 0.0);
+        let at = |time| animation.at(time).transform_point(&p);
+
+        assert!((at(-1.0) - Point::new(0.0, 2.0, 0.0)).near_zero());
+        assert!((at(0.0) - Point::new(0.0, 2.0, 0.0)).near_zero());
+        assert!((at(1.0) - Point::new(1.0, 2.0, 0.0)).near_zero());
+        assert!((at(2.0) - Point::new(1.0, 2.0, 0.0)).near_zero());
+
+        // halfway: scaled by 1.5, rotated by 45 degrees and moved up by 1
+        let s = 1.5 * std::f64::consts::FRAC_1_SQRT_2;
+        assert!((at(0.5) - Point::new(s, s + 1.0, 0.0)).near_zero());
+
+        // mirrored in both keyframes is fine, mirroring on the way isn't
+        let mirrored =
+            |x: f64| Keyframe::new(0.0, Vec3::zeros(), Quat::identity(), [x, 1.0, 1.0].into());
+        let frames = |a: f64, b: f64| {
+            vec![
+                mirrored(a),
+                Keyframe {
+                    time: 1.0,
+                    ..mirrored(b)
+                },
+            ]
+        };
+        assert!(AnimatedTransform::try_new(frames(-1.0, -2.0)).is_ok());
+        assert!(AnimatedTransform::try_new(frames(1.0, -1.0)).is_err());
+        assert!(AnimatedTransform::try_new(frames(0.0, 1.0)).is_err());
+        assert!(AnimatedTransform::try_new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let cube = Cube::new(&Point::new(1.0, 1.0, 1.0), &Point::new(2.0, 3.0, 2.0), mat);
+
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::with_translation(0.0, Vec3::zeros()),
+            Keyframe::new(
+                0.5,
+                [3.0, 0.0, -1.0].into(),
+                Quat::from_axis_angle([1.0, 1.0, 0.0].into(), 170.0),
+                [1.0, 0.5, 2.0].into(),
+            ),
+            Keyframe::new(
+                1.0,
+                [0.0, 4.0, 0.0].into(),
+                Quat::from_axis_angle([0.0, 0.0, 1.0].into(), -120.0),
+                Vec3::ones(),
+            ),
+        ]);
+        let obj = Animated::new(cube, animation);
+
+        for (time0, time1) in [(0.0, 1.0), (0.2, 0.7), (0.6, 0.6)] {
+            let bbox = obj.bounding_box(time0, time1).unwrap();
+
+            for _ in 0..2000 {
+                let time = rand_range(time0..=time1);
+                let r = Ray::with_time(
+                    Point::random_range(-10.0..10.0),
+                    Vec3::random_unit_vector(),
+                    time,
+                );
+
+                if let Some(rec) = obj.hit(&r, 0.001, f64::INFINITY) {
+                    for axis in 0..3 {
+                        let v = rec.p.data()[axis];
+                        assert!(
+                            bbox.min().data()[axis] - 1e-9 <= v
+                                && v <= bbox.max().data()[axis] + 1e-9,
+                            "hit {:?} at time {} is outside of {:?}",
+                            rec.p,
+                            time,
+                            bbox
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_light() {
+        let light = DiffuseLight::new([4.0, 4.0, 4.0].into());
+        let panel = rect::XZ::new(light, (-1.0, 1.0), (-1.0, 1.0), 0.0);
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::with_translation(0.0, [0.0, 3.0, 0.0].into()),
+            Keyframe::with_translation(1.0, [5.0, 3.0, 0.0].into()),
+        ]);
+
+        let mut world = HittableList::default();
+        world.add(Cube::new(
+            &Point::new(-1.0, -1.0, -1.0),
+            &Point::new(1.0, 0.0, 1.0),
+            Arc::new(Lambertian::new([0.5, 0.5, 0.5].into())),
+        ));
+        world.add(Animated::new(panel, animation));
+
+        let lights = world.lights();
+        assert!(!lights.is_empty());
+
+        // the panel is sampled where it is at time zero, 3 units above
+        let origin = Point::zeros();
+        for _ in 0..100 {
+            let d = lights.random(&origin);
+            let r = Ray::new(origin, d);
+            let rec = lights.hit(&r, 0.001, f64::INFINITY).unwrap();
+            assert!((rec.p.y() - 3.0).abs() < 1e-9);
+            assert!(lights.pdf_value(&origin, &d) > 0.0);
+        }
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let expected = 3.0 * 3.0 / 4.0;
+        assert!((lights.pdf_value(&origin, &up) - expected).abs() < 1e-9);
+    }
+}
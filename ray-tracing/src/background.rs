@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use crate::{
+    description::{self, BackgroundDescription, Describer},
+    ray::Vec3,
+    render::Color,
+};
+
+/// The light arriving along rays which don't hit anything.
+///
+/// A background can also be a light, then the integrators pick directions
+/// towards it like they do for the emitting objects.
+pub trait Background: Send + Sync {
+    /// The light coming from the given direction, which doesn't have to be
+    /// normalized.
+    fn value(&self, direction: &Vec3) -> Color;
+
+    /// Whether directions towards the background are sampled.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// The density, with respect to the solid angle, of `random` picking the
+    /// given direction.
+    fn pdf_value(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// A random direction towards the background.
+    fn random(&self) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// A serializable description of the background.
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<BackgroundDescription> {
+        description::not_describable(std::any::type_name::<Self>())
+    }
+}
+
+impl<B: Background + ?Sized> Background for Arc<B> {
+    fn value(&self, direction: &Vec3) -> Color {
+        (**self).value(direction)
+    }
+
+    fn is_light(&self) -> bool {
+        (**self).is_light()
+    }
+
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        (**self).pdf_value(direction)
+    }
+
+    fn random(&self) -> Vec3 {
+        (**self).random()
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<BackgroundDescription> {
+        (**self).describe(d)
+    }
+}
+
+/// The same color in every direction.
+impl Background for Color {
+    fn value(&self, _direction: &Vec3) -> Color {
+        *self
+    }
+}
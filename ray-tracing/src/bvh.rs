@@ -1,21 +1,79 @@
 use std::{cmp::Ordering, sync::Arc};
 
+use rayon::prelude::*;
+
 use crate::{
     aabb::Aabb,
+    description::{Describer, ObjectDescription},
     hittable::{HitRecord, Hittable, HittableList, HittableObject},
     rand_range,
-    ray::Ray,
+    ray::{Point, Ray},
+    stream_seed,
 };
 
+/// How the objects of a node are divided between its two children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitMethod {
+    /// Sort the objects along a random axis and split them at the median.
+    #[default]
+    Median,
+    /// Bin the centroids of the objects along every axis and split where
+    /// the surface area heuristic is the lowest.
+    Sah,
+}
+
+/// The number of buckets used to evaluate the surface area heuristic.
+const SAH_BINS: usize = 12;
+
+/// Subtrees with fewer objects are built on the current thread, as spawning
+/// a task would cost more than it saves.
+const PARALLEL_BUILD_SIZE: usize = 1024;
+
+/// The cost of visiting a node relative to intersecting an object, used to
+/// estimate the quality of a tree.
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// A refitted tree whose cost grew past this factor of its cost after the
+/// build should be rebuilt.
+pub const REBUILD_RATIO: f64 = 1.5;
+
+#[derive(Clone)]
+enum Child {
+    Node(Arc<BvhNode>),
+    Object(HittableObject),
+}
+
+impl Child {
+    fn get(&self) -> &dyn Hittable {
+        match self {
+            Self::Node(node) => &**node,
+            Self::Object(obj) => &**obj,
+        }
+    }
+
+    fn hittable(&self) -> HittableObject {
+        match self {
+            Self::Node(node) => node.clone(),
+            Self::Object(obj) => obj.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct BvhNode {
-    left: HittableObject,
-    right: HittableObject,
+    left: Child,
+    right: Child,
     ibox: Aabb,
 }
 
 impl BvhNode {
     pub fn from_hittable_list(list: &HittableList, time0: f64, time1: f64) -> Self {
-        Self::from_list(&list.objects(), 0, list.objects().len(), time0, time1)
+        Self::with_split(list, time0, time1, SplitMethod::Median)
+    }
+
+    /// Builds the tree using the given split method.
+    pub fn with_split(list: &HittableList, time0: f64, time1: f64, split: SplitMethod) -> Self {
+        Self::build_slice(list.objects(), time0, time1, split)
     }
 
     pub fn from_list(
@@ -25,77 +83,151 @@ impl BvhNode {
         time0: f64,
         time1: f64,
     ) -> Self {
-        // Create a modifable array of the source scene objects
-        Self::inner_from_list(&mut objects.to_vec(), start, end, time0, time1)
+        Self::build_slice(&objects[start..end], time0, time1, SplitMethod::Median)
+    }
+
+    fn build_slice(objects: &[HittableObject], time0: f64, time1: f64, split: SplitMethod) -> Self {
+        assert!(!objects.is_empty(), "a bvh node needs at least one object");
+
+        let mut prims = Primitive::collect(objects, time0, time1);
+
+        // The random axes are derived from a single draw, so the tree doesn't
+        // depend on which thread builds which subtree.
+        let seed = rand_range(0..=u64::MAX);
+
+        Self::inner_from_list(objects, &mut prims, split, seed, 0)
     }
 
     fn inner_from_list(
-        objects: &mut [HittableObject],
-        start: usize,
-        end: usize,
-        time0: f64,
-        time1: f64,
+        objects: &[HittableObject],
+        prims: &mut [Primitive],
+        split: SplitMethod,
+        seed: u64,
+        node: u64,
     ) -> Self {
-        let comparator: &dyn Fn(&HittableObject, &HittableObject) -> Ordering;
-        comparator = match rand_range(0..=2) {
-            0 => &box_x_compare,
-            1 => &box_y_compare,
-            _ => &box_z_compare,
+        let axis = (stream_seed(seed, node, 0) % 3) as usize;
+        let comparator = |a: &Primitive, b: &Primitive| {
+            f64::total_cmp(&a.bbox.min().data()[axis], &b.bbox.min().data()[axis])
         };
+        let object = |p: &Primitive| Child::Object(objects[p.index].clone());
 
-        assert!(start < end, "start should be smaller then the end");
+        let (left, right, ibox) = match prims {
+            [] => unreachable!("a bvh node needs at least one object"),
+            [a] => (object(a), object(a), a.bbox.clone()),
+            [a, b] => {
+                let ibox = Aabb::surrounding_box(&a.bbox, &b.bbox);
+                match comparator(a, b) {
+                    Ordering::Greater => (object(b), object(a), ibox),
+                    _ => (object(a), object(b), ibox),
+                }
+            }
+            prims => {
+                let len = prims.len();
+                let mid = match split {
+                    SplitMethod::Median => {
+                        prims.sort_unstable_by(comparator);
+                        len / 2
+                    }
+                    SplitMethod::Sah => sah_partition(prims),
+                };
 
-        let object_span = end - start;
+                let (left, right) = prims.split_at_mut(mid);
+                let build = |prims: &mut [Primitive], child| {
+                    Self::inner_from_list(objects, prims, split, seed, child)
+                };
+                let (l, r) = (node.wrapping_mul(2) + 1, node.wrapping_mul(2) + 2);
 
-        let (left, right) = match object_span {
-            1 => (objects[start].clone(), objects[start].clone()),
-            2 => match comparator(&objects[start], &objects[start + 1]) {
-                Ordering::Greater => (objects[start + 1].clone(), objects[start].clone()),
-                _ => (objects[start].clone(), objects[start + 1].clone()),
-            },
-            _ => {
-                // Don't care about reordering here
-                objects.sort_unstable_by(comparator);
+                let (left, right) = if len >= PARALLEL_BUILD_SIZE {
+                    rayon::join(|| build(left, l), || build(right, r))
+                } else {
+                    (build(left, l), build(right, r))
+                };
 
-                let mid = start + object_span / 2;
+                let ibox = Aabb::surrounding_box(&left.ibox, &right.ibox);
+                (
+                    Child::Node(Arc::new(left)),
+                    Child::Node(Arc::new(right)),
+                    ibox,
+                )
+            }
+        };
 
-                let left = Arc::new(Self::inner_from_list(objects, start, mid, time0, time1));
-                let right = Arc::new(Self::inner_from_list(objects, mid, end, time0, time1));
+        Self { left, right, ibox }
+    }
 
-                (left as Arc<dyn Hittable>, right as Arc<dyn Hittable>)
+    /// Recomputes the bounding boxes bottom up for the given time interval,
+    /// keeping the structure of the tree.
+    ///
+    /// This is much cheaper than a rebuild, but the tree gets worse the more
+    /// the objects moved since the build, see [`BvhNode::needs_rebuild`].
+    /// Subtrees shared with someone else are copied first.
+    pub fn refit(&mut self, time0: f64, time1: f64) {
+        let refit = |child: &mut Child| match child {
+            Child::Node(node) => {
+                let node = Arc::make_mut(node);
+                node.refit(time0, time1);
+                node.ibox.clone()
             }
+            Child::Object(obj) => obj
+                .bounding_box(time0, time1)
+                .expect("No bounding box in bvh_node refit."),
         };
 
-        let (box_left, box_right) = Self::check_cond(&left, &right, (time0, time1))
-            .expect("No bounding box in bvh_node constructor.");
-
-        let ibox = Aabb::surrounding_box(&box_left, &box_right);
+        let left = refit(&mut self.left);
+        let right = refit(&mut self.right);
+        self.ibox = Aabb::surrounding_box(&left, &right);
+    }
 
-        Self { left, right, ibox }
+    /// The expected cost of a ray intersecting the tree according to the
+    /// surface area heuristic, in units of object intersections.
+    pub fn sah_cost(&self) -> f64 {
+        let area = self.ibox.surface_area();
+        if area > 0.0 {
+            self.area_cost() / area
+        } else {
+            // a flat tree is hit by no ray
+            0.0
+        }
     }
 
-    fn check_cond(
-        left: &HittableObject,
-        right: &HittableObject,
-        time: (f64, f64),
-    ) -> Option<(Aabb, Aabb)> {
-        let l = left.bounding_box(time.0, time.1);
-        let r = right.bounding_box(time.0, time.1);
+    /// The cost of the subtree scaled by the surface area of the root.
+    fn area_cost(&self) -> f64 {
+        let mut cost = TRAVERSAL_COST * self.ibox.surface_area();
 
-        match (l, r) {
-            (Some(left), Some(right)) => Some((left, right)),
-            _ => None,
+        for child in self.children() {
+            cost += match child {
+                Child::Node(node) => node.area_cost(),
+                // an object is tested by every ray hitting its parent
+                Child::Object(_) => self.ibox.surface_area(),
+            };
         }
+
+        cost
+    }
+
+    /// The distinct children, a node with a single object stores it on both
+    /// sides.
+    fn children(&self) -> Vec<&Child> {
+        match (&self.left, &self.right) {
+            (Child::Object(l), Child::Object(r)) if Arc::ptr_eq(l, r) => vec![&self.left],
+            _ => vec![&self.left, &self.right],
+        }
+    }
+
+    /// Whether the tree degraded enough since it was built with the given
+    /// [`BvhNode::sah_cost`] that a rebuild pays off.
+    pub fn needs_rebuild(&self, built_cost: f64) -> bool {
+        self.sah_cost() > REBUILD_RATIO * built_cost
     }
 
     /// Get a clone to the bvh node's left.
     pub fn left(&self) -> HittableObject {
-        self.left.clone()
+        self.left.hittable()
     }
 
     /// Get a clone to the bvh node's right.
     pub fn right(&self) -> HittableObject {
-        self.right.clone()
+        self.right.hittable()
     }
 
     /// Get a reference to the bvh node's ibox.
@@ -113,7 +245,7 @@ impl Hittable for BvhNode {
         if !self.ibox.hit(r, t_min, t_max) {
             return None;
         }
-        let hit_left = self.left.hit(r, t_min, t_max);
+        let hit_left = self.left.get().hit(r, t_min, t_max);
 
         let using = if let Some(rec) = &hit_left {
             rec.t
@@ -121,7 +253,7 @@ impl Hittable for BvhNode {
             t_max
         };
 
-        let hit_right = self.right.hit(r, t_min, using);
+        let hit_right = self.right.get().hit(r, t_min, using);
 
         match (hit_left, hit_right) {
             (None, val) | (val, None) => val,
@@ -134,33 +266,551 @@ impl Hittable for BvhNode {
             }
         }
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        // The tree is rebuilt when loading, so only the leaves are kept.
+        let mut objects = Vec::new();
+        for child in self.children() {
+            match child.get().describe(d)? {
+                ObjectDescription::Bvh { objects: inner } => objects.extend(inner),
+                desc => objects.push(desc),
+            }
+        }
+
+        Ok(ObjectDescription::Bvh { objects })
+    }
 }
 
-fn box_compare(a: &HittableObject, b: &HittableObject, axis: usize) -> Ordering {
-    let (box_a, box_b) = BvhNode::check_cond(a, b, (0.0, 0.0)).unwrap_or_else(|| {
-        panic!(
-            "No bounding box in bvh_node box compare found during a box compare with axis {}.",
-            axis
-        )
-    });
+/// The maximal number of objects stored in a leaf of a [`LinearBvh`].
+const MAX_LEAF_SIZE: usize = 4;
 
-    // As there currently is no total_cmp implemented
-    // we are going to assume that no illegal values
-    // are used here
-    let left = box_a.min().data()[axis];
-    let right = box_b.min().data()[axis];
+/// Past this depth nodes are split at the median, which bounds the depth of
+/// the tree and with it the size of the traversal stack.
+const MAX_SAH_DEPTH: usize = 32;
 
-    f64::partial_cmp(&left, &right).expect("illegal f64 value was used here, no NaN or Inf allowed")
+/// The traversal stack size, deep enough for any tree built here.
+const STACK_SIZE: usize = 64;
+
+enum LinearNode {
+    Leaf {
+        bbox: Aabb,
+        start: usize,
+        count: usize,
+    },
+    /// The first child directly follows its parent in the array.
+    Interior {
+        bbox: Aabb,
+        second_child: usize,
+        axis: usize,
+    },
 }
 
-fn box_x_compare(a: &HittableObject, b: &HittableObject) -> Ordering {
-    box_compare(a, b, 0)
+impl LinearNode {
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Self::Leaf { bbox, .. } | Self::Interior { bbox, .. } => bbox,
+        }
+    }
 }
 
-fn box_y_compare(a: &HittableObject, b: &HittableObject) -> Ordering {
-    box_compare(a, b, 1)
+/// A bvh stored as a flat array of nodes in depth first order.
+///
+/// The objects of a leaf are stored next to each other, so a leaf only needs
+/// a range. Traversal uses an explicit stack and visits the child closer to
+/// the ray origin first, which allows skipping the other one more often.
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+    objects: Vec<HittableObject>,
 }
 
-fn box_z_compare(a: &HittableObject, b: &HittableObject) -> Ordering {
-    box_compare(a, b, 2)
+impl LinearBvh {
+    pub fn new(list: &HittableList, time0: f64, time1: f64) -> Self {
+        Self::with_split(list, time0, time1, SplitMethod::Sah)
+    }
+
+    /// Builds the tree using the given split method, the median split uses
+    /// the widest axis instead of a random one.
+    pub fn with_split(list: &HittableList, time0: f64, time1: f64, split: SplitMethod) -> Self {
+        let objects = list.objects();
+        let mut prims = Primitive::collect(objects, time0, time1);
+
+        let mut nodes = Vec::with_capacity(2 * prims.len());
+        if !prims.is_empty() {
+            Self::build(&mut nodes, &mut prims, 0, split, 0);
+        }
+
+        Self {
+            nodes,
+            objects: prims.iter().map(|p| objects[p.index].clone()).collect(),
+        }
+    }
+
+    /// Appends the subtree of `prims`, which start at `offset` in the final
+    /// object array.
+    fn build(
+        nodes: &mut Vec<LinearNode>,
+        prims: &mut [Primitive],
+        offset: usize,
+        split: SplitMethod,
+        depth: usize,
+    ) {
+        let (bbox, centroids) = bounds(prims);
+
+        if prims.len() <= MAX_LEAF_SIZE {
+            nodes.push(LinearNode::Leaf {
+                bbox,
+                start: offset,
+                count: prims.len(),
+            });
+            return;
+        }
+
+        let extent = *centroids.max() - *centroids.min();
+        let axis = (0..3)
+            .max_by(|&a, &b| f64::total_cmp(&extent.data()[a], &extent.data()[b]))
+            .unwrap_or(0);
+
+        let mid = match split {
+            SplitMethod::Sah if depth < MAX_SAH_DEPTH => sah_partition(prims),
+            _ => {
+                let mid = prims.len() / 2;
+                prims.select_nth_unstable_by(mid, |a, b| {
+                    f64::total_cmp(&a.centroid.data()[axis], &b.centroid.data()[axis])
+                });
+                mid
+            }
+        };
+
+        let len = prims.len();
+        let index = nodes.len();
+        nodes.push(LinearNode::Interior {
+            bbox,
+            second_child: 0,
+            axis,
+        });
+
+        let (left, right) = prims.split_at_mut(mid);
+        let second = if len >= PARALLEL_BUILD_SIZE {
+            // The second subtree is built into its own array and appended
+            // afterwards, its child indices are relative to its root.
+            let mut right_nodes = Vec::with_capacity(2 * right.len());
+            rayon::join(
+                || Self::build(nodes, left, offset, split, depth + 1),
+                || Self::build(&mut right_nodes, right, offset + mid, split, depth + 1),
+            );
+
+            let second = nodes.len();
+            nodes.extend(right_nodes.into_iter().map(|mut node| {
+                if let LinearNode::Interior { second_child, .. } = &mut node {
+                    *second_child += second;
+                }
+                node
+            }));
+            second
+        } else {
+            Self::build(nodes, left, offset, split, depth + 1);
+
+            let second = nodes.len();
+            Self::build(nodes, right, offset + mid, split, depth + 1);
+            second
+        };
+
+        if let LinearNode::Interior { second_child, .. } = &mut nodes[index] {
+            *second_child = second;
+        }
+    }
+
+    /// Recomputes the bounding boxes bottom up for the given time interval,
+    /// keeping the structure of the tree, see [`BvhNode::refit`].
+    pub fn refit(&mut self, time0: f64, time1: f64) {
+        // children are stored after their parent
+        for index in (0..self.nodes.len()).rev() {
+            let bbox = match self.nodes[index] {
+                LinearNode::Leaf { start, count, .. } => self.objects[start..start + count]
+                    .iter()
+                    .map(|obj| {
+                        obj.bounding_box(time0, time1)
+                            .expect("No bounding box in linear bvh refit.")
+                    })
+                    .reduce(|acc, b| Aabb::surrounding_box(&acc, &b))
+                    .expect("a leaf needs at least one object"),
+                LinearNode::Interior { second_child, .. } => Aabb::surrounding_box(
+                    self.nodes[index + 1].bbox(),
+                    self.nodes[second_child].bbox(),
+                ),
+            };
+
+            match &mut self.nodes[index] {
+                LinearNode::Leaf { bbox: b, .. } | LinearNode::Interior { bbox: b, .. } => {
+                    *b = bbox
+                }
+            }
+        }
+    }
+
+    /// The expected cost of a ray intersecting the tree, see
+    /// [`BvhNode::sah_cost`].
+    pub fn sah_cost(&self) -> f64 {
+        let area = match self.nodes.first() {
+            Some(root) => root.bbox().surface_area(),
+            None => return 0.0,
+        };
+        if area <= 0.0 {
+            return 0.0;
+        }
+
+        let cost: f64 = self
+            .nodes
+            .iter()
+            .map(|node| match *node {
+                LinearNode::Leaf {
+                    ref bbox, count, ..
+                } => count as f64 * bbox.surface_area(),
+                LinearNode::Interior { ref bbox, .. } => TRAVERSAL_COST * bbox.surface_area(),
+            })
+            .sum();
+
+        cost / area
+    }
+
+    /// Whether the tree degraded enough since it was built with the given
+    /// [`LinearBvh::sah_cost`] that a rebuild pays off.
+    pub fn needs_rebuild(&self, built_cost: f64) -> bool {
+        self.sah_cost() > REBUILD_RATIO * built_cost
+    }
+
+    /// Get the number of nodes in the tree.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl Hittable for LinearBvh {
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox().clone())
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, mut t_max: f64) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let dir_is_neg = [0, 1, 2].map(|axis| r.direction().data()[axis] < 0.0);
+
+        let mut closest = None;
+        let mut stack = [0; STACK_SIZE];
+        let mut len = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+
+            if node.bbox().hit(r, t_min, t_max) {
+                match *node {
+                    LinearNode::Leaf { start, count, .. } => {
+                        for obj in &self.objects[start..start + count] {
+                            if let Some(rec) = obj.hit(r, t_min, t_max) {
+                                t_max = rec.t;
+                                closest = Some(rec);
+                            }
+                        }
+                    }
+                    LinearNode::Interior {
+                        second_child, axis, ..
+                    } => {
+                        // Visit the child on the side the ray comes from first.
+                        let (near, far) = if dir_is_neg[axis] {
+                            (second_child, current + 1)
+                        } else {
+                            (current + 1, second_child)
+                        };
+
+                        stack[len] = far;
+                        len += 1;
+                        current = near;
+                        continue;
+                    }
+                }
+            }
+
+            if len == 0 {
+                break;
+            }
+            len -= 1;
+            current = stack[len];
+        }
+
+        closest
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let objects = self
+            .objects
+            .iter()
+            .map(|obj| obj.describe(d))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(ObjectDescription::Bvh { objects })
+    }
+}
+
+/// An object together with its bounds, computed once per build.
+#[derive(Clone)]
+struct Primitive {
+    index: usize,
+    bbox: Aabb,
+    centroid: Point,
+}
+
+impl Primitive {
+    fn collect(objects: &[HittableObject], time0: f64, time1: f64) -> Vec<Self> {
+        objects
+            .par_iter()
+            .enumerate()
+            .map(|(index, obj)| {
+                let bbox = obj
+                    .bounding_box(time0, time1)
+                    .expect("No bounding box in bvh_node constructor.");
+                let centroid = bbox.centroid();
+                Self {
+                    index,
+                    bbox,
+                    centroid,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The bounds of all the primitives and of their centroids.
+fn bounds(prims: &[Primitive]) -> (Aabb, Aabb) {
+    let first = &prims[0];
+    prims.iter().skip(1).fold(
+        (
+            first.bbox.clone(),
+            Aabb::new(first.centroid, first.centroid),
+        ),
+        |(bbox, centroids), p| {
+            (
+                Aabb::surrounding_box(&bbox, &p.bbox),
+                Aabb::surrounding_box(&centroids, &Aabb::new(p.centroid, p.centroid)),
+            )
+        },
+    )
+}
+
+/// Reorders the primitives so the ones left of the cheapest split come first,
+/// returns the number of primitives on the left.
+fn sah_partition(prims: &mut [Primitive]) -> usize {
+    let (_, bounds) = bounds(prims);
+
+    let extent = *bounds.max() - *bounds.min();
+    let bin = |c: &Point, axis: usize| {
+        let offset = (c.data()[axis] - bounds.min().data()[axis]) / extent.data()[axis];
+        ((offset * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+    };
+
+    // The cost of the traversal step is the same for every split, so only the
+    // cost of intersecting the children is compared.
+    let cost = |bins: &[(usize, Option<Aabb>)]| {
+        let count: usize = bins.iter().map(|(count, _)| count).sum();
+        let bbox = bins
+            .iter()
+            .filter_map(|(_, bbox)| bbox.clone())
+            .reduce(|acc, b| Aabb::surrounding_box(&acc, &b));
+        bbox.map(|bbox| count as f64 * bbox.surface_area())
+    };
+
+    let mut best: Option<(f64, usize, usize)> = None;
+
+    // Axes where all the centroids are at the same spot can't be split.
+    for axis in (0..3).filter(|&axis| extent.data()[axis] > 0.0) {
+        let mut bins: [(usize, Option<Aabb>); SAH_BINS] = Default::default();
+        for p in prims.iter() {
+            let (count, bbox) = &mut bins[bin(&p.centroid, axis)];
+            *count += 1;
+            *bbox = Some(match bbox {
+                Some(bbox) => Aabb::surrounding_box(bbox, &p.bbox),
+                None => p.bbox.clone(),
+            });
+        }
+
+        for split in 1..SAH_BINS {
+            let total = match (cost(&bins[..split]), cost(&bins[split..])) {
+                (Some(left), Some(right)) => left + right,
+                _ => continue,
+            };
+
+            if best.is_none_or(|(cost, _, _)| total < cost) {
+                best = Some((total, axis, split));
+            }
+        }
+    }
+
+    let (axis, split) = match best {
+        Some((_, axis, split)) => (axis, split),
+        None => return prims.len() / 2,
+    };
+
+    // A stable partition keeps the build deterministic.
+    prims.sort_by_key(|p| bin(&p.centroid, axis) >= split);
+    prims
+        .iter()
+        .take_while(|p| bin(&p.centroid, axis) < split)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::Lambertian,
+        objects::{MovingSphere, Sphere},
+        ray::Vec3,
+    };
+
+    fn random_spheres(count: usize) -> HittableList {
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut list = HittableList::with_capacity(count);
+        for _ in 0..count {
+            let center = Point::random_range(-10.0..10.0);
+            list.add(Sphere::new(center, rand_range(0.1..1.0), mat.clone()));
+        }
+        list
+    }
+
+    #[test]
+    fn test_matches_list() {
+        let list = random_spheres(200);
+
+        for split in [SplitMethod::Median, SplitMethod::Sah] {
+            let bvh = BvhNode::with_split(&list, 0.0, 1.0, split);
+            let linear = LinearBvh::with_split(&list, 0.0, 1.0, split);
+
+            for _ in 0..500 {
+                let r = Ray::new(Point::random_range(-15.0..15.0), Vec3::random_unit_vector());
+
+                let expected = list.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                let actual = bvh.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                assert_eq!(actual, expected, "{:?} missed an object", split);
+
+                let actual = linear.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                assert_eq!(actual, expected, "linear {:?} missed an object", split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_build() {
+        let list = random_spheres(4 * PARALLEL_BUILD_SIZE);
+
+        // the leaf order of the description reflects the shape of the tree
+        let describe = |split| {
+            crate::seed_rng(7);
+            let bvh = BvhNode::with_split(&list, 0.0, 1.0, split);
+            let linear = LinearBvh::with_split(&list, 0.0, 1.0, split);
+            let mut d = Describer::new(".");
+            (
+                bvh.describe(&mut d).unwrap(),
+                linear.describe(&mut d).unwrap(),
+                bvh,
+                linear,
+            )
+        };
+
+        for split in [SplitMethod::Median, SplitMethod::Sah] {
+            let (bvh_desc, linear_desc, bvh, linear) = describe(split);
+            let (bvh_again, linear_again, _, _) = describe(split);
+            assert_eq!(bvh_desc, bvh_again, "{:?} isn't deterministic", split);
+            assert_eq!(
+                linear_desc, linear_again,
+                "linear {:?} isn't deterministic",
+                split
+            );
+
+            for _ in 0..200 {
+                let r = Ray::new(Point::random_range(-15.0..15.0), Vec3::random_unit_vector());
+
+                let expected = list.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                let actual = bvh.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                assert_eq!(actual, expected, "{:?} missed an object", split);
+
+                let actual = linear.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+                assert_eq!(actual, expected, "linear {:?} missed an object", split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_refit() {
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut list = HittableList::new();
+        for _ in 0..200 {
+            let center0 = Point::random_range(-10.0..10.0);
+            let center1 = center0 + Vec3::random_range(-5.0..5.0);
+            list.add(MovingSphere::new(
+                (center0, center1),
+                (0.0, 1.0),
+                0.5,
+                mat.clone(),
+            ));
+        }
+
+        let mut bvh = BvhNode::with_split(&list, 0.0, 0.0, SplitMethod::Sah);
+        let mut linear = LinearBvh::new(&list, 0.0, 0.0);
+        let (bvh_cost, linear_cost) = (bvh.sah_cost(), linear.sah_cost());
+        assert!(!bvh.needs_rebuild(bvh_cost));
+
+        // a copy of a subtree keeps the old bounds
+        let shared = bvh.clone();
+
+        bvh.refit(1.0, 1.0);
+        linear.refit(1.0, 1.0);
+
+        for _ in 0..500 {
+            let r = Ray::with_time(
+                Point::random_range(-15.0..15.0),
+                Vec3::random_unit_vector(),
+                1.0,
+            );
+
+            let expected = list.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+            let actual = bvh.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+            assert_eq!(actual, expected, "the refitted tree missed an object");
+
+            let actual = linear.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t);
+            assert_eq!(
+                actual, expected,
+                "the refitted linear tree missed an object"
+            );
+        }
+
+        assert_eq!(Some(shared.ibox().clone()), list.bounding_box(0.0, 0.0));
+        assert_eq!(Some(bvh.ibox().clone()), list.bounding_box(1.0, 1.0));
+
+        // the objects moved far apart from their neighbours in the tree
+        assert!(bvh.needs_rebuild(bvh_cost));
+        assert!(linear.needs_rebuild(linear_cost));
+    }
+
+    #[test]
+    fn test_linear_edge_cases() {
+        let empty = LinearBvh::new(&HittableList::new(), 0.0, 1.0);
+        let r = Ray::new(Point::zeros(), [0.0, 0.0, -1.0].into());
+        assert!(empty.hit(&r, 0.001, f64::INFINITY).is_none());
+        assert!(empty.bounding_box(0.0, 1.0).is_none());
+
+        // identical objects can't be split by their centroids
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut list = HittableList::new();
+        for _ in 0..100 {
+            list.add(Sphere::new([0.0, 0.0, -2.0].into(), 1.0, mat.clone()));
+        }
+
+        let linear = LinearBvh::new(&list, 0.0, 1.0);
+        assert_eq!(
+            linear.hit(&r, 0.001, f64::INFINITY).map(|rec| rec.t),
+            Some(1.0)
+        );
+    }
 }
@@ -1,5 +1,7 @@
 use crate::{
-    degrees_to_radians, rand_range,
+    degrees_to_radians,
+    description::CameraDescription,
+    rand_range,
     ray::{Point, Ray, Vec3},
 };
 
@@ -14,9 +16,12 @@ pub struct Camera {
     lens_radius: f64,
     time0: f64,
     time1: f64,
+    /// The parameters the camera was created with.
+    desc: CameraDescription,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point,
         lookat: Point,
@@ -43,6 +48,17 @@ impl Camera {
         let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
         let lens_radius = aperture / 2.0;
 
+        let desc = CameraDescription {
+            lookfrom: lookfrom.into(),
+            lookat: lookat.into(),
+            vup: vup.into(),
+            vfov,
+            aperture,
+            focus_dist,
+            time0,
+            time1,
+        };
+
         Self {
             origin,
             horizontal,
@@ -54,9 +70,15 @@ impl Camera {
             lens_radius,
             time0,
             time1,
+            desc,
         }
     }
 
+    /// A serializable description of the camera, the aspect ratio is part of the config.
+    pub fn describe(&self) -> CameraDescription {
+        self.desc.clone()
+    }
+
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
         let rd = self.lens_radius * Vec3::random_in_unit_disk();
         let offset = self.u * rd.x() + self.v * rd.y();
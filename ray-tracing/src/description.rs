@@ -0,0 +1,594 @@
+//! Serializable descriptions of a scene and everything in it.
+//!
+//! Objects, materials and textures can describe themselves through
+//! [`Hittable::describe`], [`Material::describe`] and [`Texture::describe`],
+//! the [`Describer`] collects the named materials, textures and shared objects
+//! while doing so.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+pub use crate::profile::Sides;
+use crate::{
+    camera::Camera,
+    hittable::{Hittable, HittableObject},
+    material::Material,
+    texture::Texture,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub config: ConfigDescription,
+    pub camera: CameraDescription,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDescription>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    /// Objects placed by [`ObjectDescription::Instance`]s, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shared: BTreeMap<String, ObjectDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightDescription>,
+}
+
+/// Overrides of the default [`Config`](crate::Config).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigDescription {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples_per_pixel: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamma: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Replaces the background color.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<BackgroundDescription>,
+}
+
+/// A background replacing the constant color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BackgroundDescription {
+    EnvironmentMap {
+        path: String,
+        /// In degrees around the y axis.
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    /// The [`Sky`](crate::sky::Sky), the sun is given in degrees.
+    Sky {
+        sun_elevation: f64,
+        #[serde(default)]
+        sun_azimuth: f64,
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+        #[serde(default = "default_sun_size")]
+        sun_size: f64,
+    },
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_sun_size() -> f64 {
+    0.53
+}
+
+/// A light without a surface, see [`light`](crate::light).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Point {
+        position: [f64; 3],
+        intensity: [f64; 3],
+    },
+    /// The angles are in degrees from the axis.
+    Spot {
+        position: [f64; 3],
+        direction: [f64; 3],
+        intensity: [f64; 3],
+        cone_angle: f64,
+        falloff_angle: f64,
+    },
+    /// The direction is the way the light travels.
+    Directional {
+        direction: [f64; 3],
+        irradiance: [f64; 3],
+    },
+}
+
+/// The parameters of [`Camera::new`](crate::camera::Camera::new), the aspect
+/// ratio is taken from the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub lookfrom: [f64; 3],
+    pub lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    pub vup: [f64; 3],
+    #[serde(default = "default_vfov")]
+    pub vfov: f64,
+    #[serde(default)]
+    pub aperture: f64,
+    #[serde(default = "default_focus_dist")]
+    pub focus_dist: f64,
+    #[serde(default)]
+    pub time0: f64,
+    #[serde(default = "default_time1")]
+    pub time1: f64,
+}
+
+impl CameraDescription {
+    /// Creates the described camera.
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.lookfrom.into(),
+            self.lookat.into(),
+            self.vup.into(),
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+}
+
+fn default_vup() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f64 {
+    20.0
+}
+
+fn default_focus_dist() -> f64 {
+    10.0
+}
+
+fn default_time1() -> f64 {
+    1.0
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_rotation() -> [f64; 4] {
+    [1.0, 0.0, 0.0, 0.0]
+}
+
+fn default_scale3() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// Either the name of a texture or a solid color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureRef {
+    Color([f64; 3]),
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    Solid {
+        color: [f64; 3],
+    },
+    Checker {
+        even: TextureRef,
+        odd: TextureRef,
+    },
+    Noise {
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    Image {
+        path: String,
+    },
+    /// The vertex colors of the mesh the material is used on.
+    VertexColor,
+}
+
+impl TextureDescription {
+    /// The type of the texture, used to generate readable names.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Solid { .. } => "solid",
+            Self::Checker { .. } => "checker",
+            Self::Noise { .. } => "noise",
+            Self::Image { .. } => "image",
+            Self::VertexColor => "vertex_color",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: [f64; 3],
+        fuzz: f64,
+    },
+    Dielectric {
+        ir: f64,
+    },
+    DiffuseLight {
+        emit: TextureRef,
+        #[serde(default)]
+        sides: Sides,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<ProfileDescription>,
+    },
+    Isotropic {
+        albedo: TextureRef,
+    },
+}
+
+/// The angular profile of a [`DiffuseLight`](crate::material::DiffuseLight).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ProfileDescription {
+    /// The angles are in degrees from the normal.
+    Spot { cone_angle: f64, falloff_angle: f64 },
+    /// An IES LM-63 file.
+    Ies { path: String },
+}
+
+impl MaterialDescription {
+    /// The type of the material, used to generate readable names.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Lambertian { .. } => "lambertian",
+            Self::Metal { .. } => "metal",
+            Self::Dielectric { .. } => "dielectric",
+            Self::DiffuseLight { .. } => "diffuse_light",
+            Self::Isotropic { .. } => "isotropic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    MovingSphere {
+        center0: [f64; 3],
+        center1: [f64; 3],
+        #[serde(default)]
+        time0: f64,
+        #[serde(default = "default_time1")]
+        time1: f64,
+        radius: f64,
+        material: String,
+    },
+    Cube {
+        min: [f64; 3],
+        max: [f64; 3],
+        material: String,
+    },
+    XyRect {
+        x: [f64; 2],
+        y: [f64; 2],
+        k: f64,
+        material: String,
+    },
+    XzRect {
+        x: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: String,
+    },
+    YzRect {
+        y: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<[[f64; 3]; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<[[f64; 2]; 3]>,
+        material: String,
+    },
+    /// An indexed triangle mesh stored inline.
+    Mesh {
+        positions: Vec<[f64; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<Vec<[f64; 3]>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<Vec<[f64; 2]>>,
+        /// Used by a `vertex_color` texture of the material.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        colors: Option<Vec<[f64; 3]>>,
+        indices: Vec<[usize; 3]>,
+        material: String,
+    },
+    /// A Wavefront obj file, the materials are taken from its mtl libraries.
+    Obj {
+        path: String,
+    },
+    /// A ply file, without a material the vertex colors are used.
+    Ply {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    Stl {
+        path: String,
+        material: String,
+    },
+    List {
+        objects: Vec<ObjectDescription>,
+    },
+    Bvh {
+        objects: Vec<ObjectDescription>,
+    },
+    Translate {
+        offset: [f64; 3],
+        object: Box<ObjectDescription>,
+    },
+    RotateX {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    RotateZ {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// A rotation around an axis through the origin.
+    Rotate {
+        axis: [f64; 3],
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// An affine transformation as the rows of a 4x4 matrix.
+    Transform {
+        matrix: [[f64; 4]; 4],
+        object: Box<ObjectDescription>,
+    },
+    /// A placement of an object, see [`Instance`](crate::instance::Instance).
+    Instance {
+        matrix: [[f64; 4]; 4],
+        /// Replaces the materials of the object.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+        /// The name of the object in the shared objects, all instances of
+        /// it share one object.
+        object: String,
+    },
+    /// An object moving between keyframes, see
+    /// [`AnimatedTransform`](crate::animation::AnimatedTransform).
+    Animated {
+        keyframes: Vec<KeyframeDescription>,
+        object: Box<ObjectDescription>,
+    },
+    ConstantMedium {
+        density: f64,
+        albedo: TextureRef,
+        boundary: Box<ObjectDescription>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyframeDescription {
+    pub time: f64,
+    #[serde(default)]
+    pub translation: [f64; 3],
+    /// A quaternion as `[w, x, y, z]`.
+    #[serde(default = "default_rotation")]
+    pub rotation: [f64; 4],
+    #[serde(default = "default_scale3")]
+    pub scale: [f64; 3],
+}
+
+/// Collects the materials, textures and shared objects while describing a
+/// scene.
+///
+/// Equal materials and textures are only stored once under a generated name,
+/// as are the objects shared by instances.
+pub struct Describer {
+    base: PathBuf,
+    textures: BTreeMap<String, TextureDescription>,
+    materials: BTreeMap<String, MaterialDescription>,
+    shared: BTreeMap<String, ObjectDescription>,
+    /// The names of the stored descriptions by their debug output, as the
+    /// floats they contain can't be hashed. Floats are printed exactly, so
+    /// equal outputs mean equal descriptions.
+    texture_names: HashMap<String, String>,
+    material_names: HashMap<String, String>,
+    /// The names of the shared objects by their address.
+    shared_names: HashMap<*const (), String>,
+}
+
+impl Describer {
+    /// Paths are written relative to `base`, the directory the description
+    /// will be saved in.
+    pub fn new<P: AsRef<Path>>(base: P) -> Self {
+        Self {
+            base: absolute(base.as_ref()),
+            textures: BTreeMap::new(),
+            materials: BTreeMap::new(),
+            texture_names: HashMap::new(),
+            material_names: HashMap::new(),
+            shared: BTreeMap::new(),
+            shared_names: HashMap::new(),
+        }
+    }
+
+    /// Describes the object.
+    pub fn object<H: Hittable + ?Sized>(&mut self, obj: &H) -> anyhow::Result<ObjectDescription> {
+        obj.describe(self)
+    }
+
+    /// Describes the material and returns the name it is stored under.
+    pub fn material<M: Material + ?Sized>(&mut self, mat: &M) -> anyhow::Result<String> {
+        let desc = mat.describe(self)?;
+
+        let key = format!("{:?}", desc);
+        if let Some(name) = self.material_names.get(&key) {
+            return Ok(name.clone());
+        }
+
+        let name = format!("{}_{}", desc.kind(), self.materials.len());
+        self.material_names.insert(key, name.clone());
+        self.materials.insert(name.clone(), desc);
+        Ok(name)
+    }
+
+    /// Describes the object shared by instances and returns the name it is
+    /// stored under.
+    pub fn shared(&mut self, obj: &HittableObject) -> anyhow::Result<String> {
+        let key = Arc::as_ptr(obj) as *const ();
+        if let Some(name) = self.shared_names.get(&key) {
+            return Ok(name.clone());
+        }
+
+        let desc = obj.describe(self)?;
+
+        let name = format!("object_{}", self.shared.len());
+        self.shared_names.insert(key, name.clone());
+        self.shared.insert(name.clone(), desc);
+        Ok(name)
+    }
+
+    /// Describes the texture, solid colors are returned inline.
+    pub fn texture<T: Texture + ?Sized>(&mut self, tex: &T) -> anyhow::Result<TextureRef> {
+        let desc = tex.describe(self)?;
+
+        if let TextureDescription::Solid { color } = desc {
+            return Ok(TextureRef::Color(color));
+        }
+
+        let key = format!("{:?}", desc);
+        if let Some(name) = self.texture_names.get(&key) {
+            return Ok(TextureRef::Named(name.clone()));
+        }
+
+        let name = format!("{}_{}", desc.kind(), self.textures.len());
+        self.texture_names.insert(key, name.clone());
+        self.textures.insert(name.clone(), desc);
+        Ok(TextureRef::Named(name))
+    }
+
+    /// Writes the path relative to the base directory if possible.
+    pub fn path(&self, path: &Path) -> String {
+        let path = absolute(path);
+        relative(&path, &self.base)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Returns all the collected textures, materials and shared objects.
+    pub fn finish(
+        self,
+    ) -> (
+        BTreeMap<String, TextureDescription>,
+        BTreeMap<String, MaterialDescription>,
+        BTreeMap<String, ObjectDescription>,
+    ) {
+        (self.textures, self.materials, self.shared)
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn relative(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+
+    // Different roots (or prefixes on windows) can't be made relative.
+    if path.first() != base.first() {
+        return None;
+    }
+
+    let common = path
+        .iter()
+        .zip(base.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut res = PathBuf::new();
+    for _ in common..base.len() {
+        res.push(Component::ParentDir);
+    }
+    for c in &path[common..] {
+        res.push(c);
+    }
+
+    Some(res)
+}
+
+/// The error returned by the default implementations of `describe`.
+pub(crate) fn not_describable<T>(name: &str) -> anyhow::Result<T> {
+    bail!("{} can not be described", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative() {
+        let rel = |p, b| relative(Path::new(p), Path::new(b)).map(|p| p.display().to_string());
+
+        assert_eq!(rel("/a/b/c.png", "/a/b").as_deref(), Some("c.png"));
+        assert_eq!(rel("/a/c.png", "/a/b/d").as_deref(), Some("../../c.png"));
+        assert_eq!(rel("/a/b/c.png", "/a").as_deref(), Some("b/c.png"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Piecewise constant distributions, used to sample images by brightness.
+
+/// A piecewise constant distribution over [0, 1) with one segment per value
+/// of the function.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Panics if `func` is empty. Negative values are taken as zero, if all
+    /// of them are zero the distribution is uniform.
+    pub fn new(func: &[f64]) -> Self {
+        assert!(!func.is_empty(), "a distribution needs at least one value");
+
+        let func: Vec<_> = func.iter().map(|&f| f.max(0.0)).collect();
+        let n = func.len() as f64;
+
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+        for f in &func {
+            cdf.push(cdf[cdf.len() - 1] + f / n);
+        }
+
+        let integral = cdf[func.len()];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// The number of segments.
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Get the integral of the function over [0, 1).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Turns a uniform `u` in [0, 1) into a point picked by the distribution,
+    /// returns the point, its density and the index of its segment.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x.min(1.0 - f64::EPSILON), self.pdf(offset), offset)
+    }
+
+    /// The density within the given segment.
+    pub fn pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise constant distribution over [0, 1)², picking the row first and
+/// the column within it second.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `func` holds `nv` rows of `nu` values each, panics if the size
+    /// doesn't match or is zero.
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        assert!(nu > 0 && nv > 0, "a distribution needs at least one value");
+        assert_eq!(func.len(), nu * nv, "the size of the values doesn't match");
+
+        let conditional: Vec<_> = func.chunks(nu).map(Distribution1D::new).collect();
+        let marginal: Vec<_> = conditional.iter().map(|c| c.integral()).collect();
+
+        Self {
+            conditional,
+            marginal: Distribution1D::new(&marginal),
+        }
+    }
+
+    /// Turns two uniform values in [0, 1) into a point `(u, v)` picked by the
+    /// distribution and its density.
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.0);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// The density at the point `(u, v)`.
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let index = |x: f64, count: usize| ((x * count as f64) as usize).min(count - 1);
+
+        let row = index(v, self.marginal.count());
+        let column = index(u, self.conditional[row].count());
+
+        self.marginal.pdf(row) * self.conditional[row].pdf(column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand_range;
+
+    #[test]
+    fn test_1d() {
+        let dist = Distribution1D::new(&[1.0, 0.0, 3.0, 0.0]);
+        assert!((dist.integral() - 1.0).abs() < 1e-9);
+
+        let mut hits = [0; 4];
+        for _ in 0..10_000 {
+            let (x, pdf, offset) = dist.sample_continuous(rand_range(0.0..1.0));
+            assert!((0.0..1.0).contains(&x));
+            assert_eq!(offset, (x * 4.0) as usize);
+            assert_eq!(pdf, dist.pdf(offset));
+            hits[offset] += 1;
+        }
+
+        assert_eq!(hits[1] + hits[3], 0);
+        assert!((hits[2] as f64 / 10_000.0 - 0.75).abs() < 0.02);
+
+        // all zero is uniform
+        let dist = Distribution1D::new(&[0.0, 0.0]);
+        assert_eq!(dist.sample_continuous(0.75), (0.75, 1.0, 1));
+    }
+
+    #[test]
+    fn test_2d() {
+        let func = [0.0, 1.0, 2.0, 0.0, 0.0, 5.0];
+        let dist = Distribution2D::new(&func, 3, 2);
+
+        for _ in 0..1000 {
+            let ((u, v), pdf) = dist.sample((rand_range(0.0..1.0), rand_range(0.0..1.0)));
+            assert!((pdf - dist.pdf(u, v)).abs() < 1e-9);
+            assert!(pdf > 0.0);
+        }
+
+        // the densities integrate to one
+        let total: f64 = (0..3)
+            .flat_map(|i| (0..2).map(move |j| (i, j)))
+            .map(|(i, j)| dist.pdf((i as f64 + 0.5) / 3.0, (j as f64 + 0.5) / 2.0) / 6.0)
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
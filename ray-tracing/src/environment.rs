@@ -0,0 +1,271 @@
+use std::{
+    f64::consts::PI,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    background::Background,
+    clamp, degrees_to_radians,
+    description::{BackgroundDescription, Describer},
+    distribution::Distribution2D,
+    helpers::loader::{self, ImageHolder},
+    rand_range,
+    ray::Vec3,
+    render::Color,
+};
+
+/// Light coming from an equirectangular image around the scene.
+///
+/// The top row of the image is straight up and the bottom row straight down,
+/// the columns go around the y axis. Directions towards the background are
+/// picked in proportion to the brightness of the pixels, so small bright
+/// parts like the sun are found by the light sampling.
+pub struct EnvironmentMap {
+    img: ImageHolder,
+    path: Option<PathBuf>,
+    /// In degrees around the y axis.
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// The pixels are given row by row from the top, panics if their number
+    /// doesn't match the size.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "the number of pixels doesn't match the size"
+        );
+
+        Self::with_image(ImageHolder::new(pixels, height, width), None)
+    }
+
+    /// Loads a Radiance `.hdr` or an OpenEXR `.exr` file, other image formats
+    /// are taken as gamma encoded.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let img = loader::read_hdr(path)?;
+        if img.width() == 0 || img.height() == 0 {
+            anyhow::bail!("the environment map {} is empty", path.display());
+        }
+
+        Ok(Self::with_image(img, Some(path.to_path_buf())))
+    }
+
+    fn with_image(img: ImageHolder, path: Option<PathBuf>) -> Self {
+        let (width, height) = (img.width(), img.height());
+
+        // rows near the poles cover a smaller solid angle
+        let mut func = Vec::with_capacity(width * height);
+        for row in 0..height {
+            let sin_theta = (PI * (row as f64 + 0.5) / height as f64).sin();
+            func.extend(img[row].iter().map(|p| luminance(p) * sin_theta));
+        }
+
+        Self {
+            distribution: Distribution2D::new(&func, width, height),
+            img,
+            path,
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Get the path the image was loaded from.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Get the rotation around the y axis in degrees.
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// Set the rotation around the y axis in degrees.
+    pub fn set_rotation(&mut self, rotation: f64) {
+        self.rotation = rotation;
+    }
+
+    /// Get the factor all the pixels are scaled by.
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// Set the factor all the pixels are scaled by.
+    pub fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    /// The image coordinates in [0, 1]² of a direction.
+    fn uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let theta = clamp(d.y(), -1.0, 1.0).acos();
+        let phi = f64::atan2(-d.z(), d.x()) + PI - degrees_to_radians(self.rotation);
+
+        (phi.rem_euclid(2.0 * PI) / (2.0 * PI), theta / PI)
+    }
+
+    /// The direction of the image coordinates `(u, v)`.
+    fn direction(&self, u: f64, v: f64) -> Vec3 {
+        let theta = v * PI;
+        let phi = u * 2.0 * PI + degrees_to_radians(self.rotation) - PI;
+
+        let sin_theta = theta.sin();
+        Vec3::new(sin_theta * phi.cos(), theta.cos(), -sin_theta * phi.sin())
+    }
+}
+
+/// The brightness of a linear color.
+fn luminance(c: &Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.uv(direction);
+        let index = |x: f64, count: usize| ((x * count as f64) as usize).min(count - 1);
+
+        let row = index(v, self.img.height());
+        let column = index(u, self.img.width());
+
+        self.intensity * self.img[row][column]
+    }
+
+    fn is_light(&self) -> bool {
+        true
+    }
+
+    /// The image is mapped onto the sphere with a solid angle of
+    /// `2 PI² sin(theta)` per unit of image area.
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random(&self) -> Vec3 {
+        let ((u, v), _) = self
+            .distribution
+            .sample((rand_range(0.0..1.0), rand_range(0.0..1.0)));
+
+        self.direction(u, v)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<BackgroundDescription> {
+        let path = match &self.path {
+            Some(path) => d.path(path),
+            None => anyhow::bail!("an environment map not loaded from a file can not be described"),
+        };
+
+        Ok(BackgroundDescription::EnvironmentMap {
+            path,
+            rotation: self.rotation,
+            intensity: self.intensity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::hdr::HdrEncoder, DynamicImage, Rgb, Rgb32FImage};
+
+    use super::*;
+
+    /// A dim sky with a small bright sun.
+    fn sky(width: usize, height: usize) -> Vec<Color> {
+        let mut pixels = vec![Color::new(0.2, 0.3, 0.5); width * height];
+        pixels[width + 3] = Color::new(500.0, 400.0, 300.0);
+        pixels
+    }
+
+    #[test]
+    fn test_directions() {
+        let mut env = EnvironmentMap::new(8, 4, sky(8, 4));
+        env.set_rotation(30.0);
+
+        for _ in 0..100 {
+            let d = Vec3::random_unit_vector();
+            let (u, v) = env.uv(&d);
+            assert!((env.direction(u, v) - d).near_zero());
+        }
+
+        assert!((env.direction(0.5, 0.0) - Vec3::new(0.0, 1.0, 0.0)).near_zero());
+    }
+
+    #[test]
+    fn test_importance_sampling() {
+        let (width, height) = (8, 4);
+        let pixels = sky(width, height);
+        let mut env = EnvironmentMap::new(width, height, pixels.clone());
+        env.set_rotation(-75.0);
+        env.set_intensity(2.0);
+
+        // the light arriving from all directions
+        let mut expected = Color::zeros();
+        for row in 0..height {
+            let (top, bottom) = (row as f64 / height as f64, (row + 1) as f64 / height as f64);
+            let solid_angle = 2.0 * PI / width as f64 * ((top * PI).cos() - (bottom * PI).cos());
+            for column in 0..width {
+                expected += 2.0 * solid_angle * pixels[row * width + column];
+            }
+        }
+
+        let n = 20_000;
+        let mut sum = Color::zeros();
+        for _ in 0..n {
+            let d = env.random();
+            let pdf = env.pdf_value(&d);
+            assert!(pdf > 0.0);
+            sum += env.value(&d) / pdf;
+        }
+        let estimate = sum / n as f64;
+
+        for (e, a) in expected.data().iter().zip(estimate.data()) {
+            assert!((e - a).abs() < 0.01 * e, "{:?} != {:?}", estimate, expected);
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("sky.hdr");
+        let pixels: Vec<_> = sky(8, 4)
+            .iter()
+            .map(|c| Rgb([c.x() as f32, c.y() as f32, c.z() as f32]))
+            .collect();
+        let file = std::fs::File::create(&path).unwrap();
+        HdrEncoder::new(file).encode(&pixels, 8, 4).unwrap();
+
+        let env = EnvironmentMap::load(&path).unwrap();
+        assert_eq!(env.path(), Some(path.as_path()));
+        let sun = env.direction(3.5 / 8.0, 1.5 / 4.0);
+        assert!((env.value(&sun) - Color::new(500.0, 400.0, 300.0)).length() < 5.0);
+
+        let path = dir.path().join("sky.exr");
+        let pixels = sky(8, 4);
+        let img = Rgb32FImage::from_fn(8, 4, |x, y| {
+            let c = pixels[y as usize * 8 + x as usize];
+            Rgb([c.x() as f32, c.y() as f32, c.z() as f32])
+        });
+        DynamicImage::ImageRgb32F(img).save(&path).unwrap();
+
+        let env = EnvironmentMap::load(&path).unwrap();
+        assert_eq!(env.path(), Some(path.as_path()));
+        assert!((env.value(&sun) - Color::new(500.0, 400.0, 300.0)).length() < 1e-3);
+
+        let missing = EnvironmentMap::load(dir.path().join("missing.exr"));
+        assert!(missing.is_err());
+
+        let path = dir.path().join("empty.hdr");
+        let file = std::fs::File::create(&path).unwrap();
+        HdrEncoder::new(file).encode(&[], 0, 0).unwrap();
+        assert!(EnvironmentMap::load(&path).is_err());
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    ops::{self, Neg},
-    usize,
-};
+use std::ops::{self, Neg};
 
 use rand::distributions::uniform::{SampleRange, SampleUniform};
 
@@ -155,6 +152,12 @@ impl<T, const N: usize> From<[T; N]> for CVec<T, N> {
     }
 }
 
+impl<T, const N: usize> From<CVec<T, N>> for [T; N] {
+    fn from(vec: CVec<T, N>) -> Self {
+        vec.data
+    }
+}
+
 impl<T, const N: usize> CVec<T, N>
 where
     T: num_traits::NumRef + Copy,
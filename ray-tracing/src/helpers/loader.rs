@@ -1,6 +1,7 @@
-use std::{ops::Index, path::Path};
+use std::{fs::File, io::BufReader, ops::Index, path::Path};
 
-use image::{io::Reader, GenericImageView};
+use anyhow::Context;
+use image::{codecs::hdr::HdrDecoder, io::Reader};
 
 use crate::render::{Color, Image, Render};
 
@@ -12,7 +13,7 @@ pub struct ImageHolder {
 }
 
 impl ImageHolder {
-    fn new(pixels: Vec<Color>, height: usize, width: usize) -> Self {
+    pub fn new(pixels: Vec<Color>, height: usize, width: usize) -> Self {
         Self {
             pixels,
             height,
@@ -71,3 +72,64 @@ pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<ImageHolder> {
 
     Ok(ImageHolder::new(pixels, height, width))
 }
+
+/// Reads an image with linear colors, Radiance `.hdr` and OpenEXR `.exr`
+/// files keep their range, other formats are taken as gamma encoded with
+/// values in [0, 1].
+pub fn read_hdr<P: AsRef<Path>>(path: P) -> anyhow::Result<ImageHolder> {
+    /// The gamma of 8 bit images.
+    const GAMMA: f64 = 2.2;
+
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("hdr") => {
+            let file =
+                File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+            let decoder = HdrDecoder::new(BufReader::new(file))?;
+            let meta = decoder.metadata();
+
+            let pixels = decoder
+                .read_image_hdr()?
+                .into_iter()
+                .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect();
+
+            Ok(ImageHolder::new(
+                pixels,
+                meta.height as usize,
+                meta.width as usize,
+            ))
+        }
+        Some("exr") => {
+            let img = Reader::open(path)
+                .with_context(|| format!("unable to open {}", path.display()))?
+                .decode()
+                .with_context(|| format!("unable to read {}", path.display()))?;
+
+            let height = img.height() as _;
+            let width = img.width() as _;
+
+            let pixels = img
+                .into_rgb32f()
+                .pixels()
+                .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect();
+
+            Ok(ImageHolder::new(pixels, height, width))
+        }
+        _ => {
+            let mut img = read(path)?;
+            for p in img.pixels.iter_mut() {
+                for v in p.data_mut() {
+                    *v = (*v / 255.0).powf(GAMMA);
+                }
+            }
+            Ok(img)
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod cvec;
 pub mod loader;
+pub mod obj;
+pub mod ply;
+pub mod stl;
@@ -0,0 +1,438 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::{FromStr, SplitWhitespace},
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{
+    ray::{Point, Vec3},
+    render::Color,
+};
+
+/// A single corner of a face, the indices are already zero based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaceVertex {
+    pub position: usize,
+    pub texcoord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+/// A run of faces sharing the same group name and material.
+#[derive(Debug, Default)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub faces: Vec<[FaceVertex; 3]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// Kd
+    pub diffuse: Color,
+    /// map_Kd, already resolved relative to the mtl file
+    pub diffuse_map: Option<PathBuf>,
+    /// Ks
+    pub specular: Color,
+    /// Ns
+    pub shininess: f64,
+    /// Ni
+    pub ior: f64,
+    /// d (or 1 - Tr)
+    pub dissolve: f64,
+    /// Ke
+    pub emission: Color,
+    /// illum
+    pub illum: Option<u32>,
+}
+
+impl MtlMaterial {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Color::zeros(),
+            shininess: 0.0,
+            ior: 1.0,
+            dissolve: 1.0,
+            emission: Color::zeros(),
+            illum: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ObjData {
+    pub positions: Vec<Point>,
+    pub normals: Vec<Vec3>,
+    pub texcoords: Vec<(f64, f64)>,
+    pub groups: Vec<ObjGroup>,
+    pub materials: HashMap<String, MtlMaterial>,
+}
+
+/// Reads an obj file together with all the mtl libraries it references.
+pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<ObjData> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)
+        .with_context(|| format!("unable to read obj file {}", path.display()))?;
+
+    parse(&src, path.parent().unwrap_or_else(|| Path::new("")))
+        .with_context(|| format!("unable to parse obj file {}", path.display()))
+}
+
+/// Parses the content of an obj file, mtl libraries are resolved relative to `base`.
+pub fn parse(src: &str, base: &Path) -> anyhow::Result<ObjData> {
+    let mut data = ObjData::default();
+    let mut current = ObjGroup::default();
+
+    // Starts a new group if the current one already contains faces.
+    let next_group = |data: &mut ObjData, current: &mut ObjGroup| {
+        let name = current.name.clone();
+        let material = current.material.clone();
+        let finished = std::mem::replace(
+            current,
+            ObjGroup {
+                name,
+                material,
+                faces: Vec::new(),
+            },
+        );
+        if !finished.faces.is_empty() {
+            data.groups.push(finished);
+        }
+    };
+
+    for (i, line) in logical_lines(src) {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        let res: anyhow::Result<()> = (|| {
+            match keyword {
+                "v" => data.positions.push(parse_floats::<3>(&mut tokens)?.into()),
+                "vn" => data.normals.push(parse_floats::<3>(&mut tokens)?.into()),
+                "vt" => {
+                    let u = parse_float(tokens.next())?;
+                    // the v coordinate is optional
+                    let v = match tokens.next() {
+                        Some(v) => parse_float(Some(v))?,
+                        None => 0.0,
+                    };
+                    data.texcoords.push((u, v));
+                }
+                "f" => {
+                    let corners = tokens
+                        .map(|t| parse_face_vertex(t, &data))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    if corners.len() < 3 {
+                        bail!("a face needs at least three vertices");
+                    }
+
+                    // Triangulate polygons as a fan around the first vertex.
+                    for w in corners[1..].windows(2) {
+                        current.faces.push([corners[0], w[0], w[1]]);
+                    }
+                }
+                "g" | "o" => {
+                    next_group(&mut data, &mut current);
+                    current.name = tokens.collect::<Vec<_>>().join(" ");
+                }
+                "usemtl" => {
+                    next_group(&mut data, &mut current);
+                    current.material = Some(rest(tokens)?);
+                }
+                "mtllib" => {
+                    // the file name may contain whitespace
+                    let file = base.join(rest(tokens)?);
+                    let src = fs::read_to_string(&file)
+                        .with_context(|| format!("unable to read mtl file {}", file.display()))?;
+                    let materials = parse_mtl(&src, file.parent().unwrap_or_else(|| Path::new("")))
+                        .with_context(|| format!("unable to parse mtl file {}", file.display()))?;
+                    data.materials.extend(materials);
+                }
+                // smoothing groups, lines, points and the like are not supported
+                _ => {}
+            }
+            Ok(())
+        })();
+
+        res.with_context(|| format!("line {}: {}", i + 1, line))?;
+    }
+
+    next_group(&mut data, &mut current);
+
+    Ok(data)
+}
+
+/// Parses the content of a mtl file, texture maps are resolved relative to `base`.
+pub fn parse_mtl(src: &str, base: &Path) -> anyhow::Result<HashMap<String, MtlMaterial>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<MtlMaterial> = None;
+
+    for (i, line) in logical_lines(src) {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = rest(tokens).with_context(|| format!("line {}: {}", i + 1, line))?;
+            if let Some(mat) = current.replace(MtlMaterial::new(name)) {
+                materials.insert(mat.name.clone(), mat);
+            }
+            continue;
+        }
+
+        let mat = match current.as_mut() {
+            Some(mat) => mat,
+            None => bail!("line {}: {} found before any newmtl", i + 1, keyword),
+        };
+
+        let res: anyhow::Result<()> = (|| {
+            match keyword {
+                "Kd" => mat.diffuse = parse_floats::<3>(&mut tokens)?.into(),
+                "Ks" => mat.specular = parse_floats::<3>(&mut tokens)?.into(),
+                "Ke" => mat.emission = parse_floats::<3>(&mut tokens)?.into(),
+                "Ns" => mat.shininess = parse_float(tokens.next())?,
+                "Ni" => mat.ior = parse_float(tokens.next())?,
+                "d" => mat.dissolve = parse_float(tokens.next())?,
+                "Tr" => mat.dissolve = 1.0 - parse_float(tokens.next())?,
+                "illum" => mat.illum = Some(parse_value(tokens.next())?),
+                "map_Kd" => {
+                    // options like -bm come before the file name, which is the last token
+                    let file = tokens
+                        .last()
+                        .ok_or_else(|| anyhow!("missing texture file name"))?;
+                    mat.diffuse_map = Some(base.join(file));
+                }
+                _ => {}
+            }
+            Ok(())
+        })();
+
+        res.with_context(|| format!("line {}: {}", i + 1, line))?;
+    }
+
+    if let Some(mat) = current {
+        materials.insert(mat.name.clone(), mat);
+    }
+
+    Ok(materials)
+}
+
+/// Iterates over all the lines with comments removed and `\` continuations joined,
+/// together with the index of the line they started on.
+fn logical_lines(src: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    let mut lines = src.lines().enumerate();
+
+    std::iter::from_fn(move || {
+        let (start, line) = lines.next()?;
+        let mut res = String::new();
+        let mut line = line;
+
+        loop {
+            let content = line.split('#').next().unwrap_or_default().trim_end();
+            match content.strip_suffix('\\') {
+                Some(content) => {
+                    res.push_str(content);
+                    res.push(' ');
+                    match lines.next() {
+                        Some((_, next)) => line = next,
+                        None => break,
+                    }
+                }
+                None => {
+                    res.push_str(content);
+                    break;
+                }
+            }
+        }
+
+        Some((start, res))
+    })
+}
+
+fn rest(tokens: SplitWhitespace<'_>) -> anyhow::Result<String> {
+    let res = tokens.collect::<Vec<_>>().join(" ");
+    if res.is_empty() {
+        bail!("missing name");
+    }
+    Ok(res)
+}
+
+fn parse_value<T>(token: Option<&str>) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let token = token.ok_or_else(|| anyhow!("missing value"))?;
+    token
+        .parse()
+        .with_context(|| format!("invalid value {:?}", token))
+}
+
+fn parse_float(token: Option<&str>) -> anyhow::Result<f64> {
+    parse_value(token)
+}
+
+fn parse_floats<const N: usize>(tokens: &mut SplitWhitespace<'_>) -> anyhow::Result<[f64; N]> {
+    let mut res = [0.0; N];
+    for v in res.iter_mut() {
+        *v = parse_float(tokens.next())?;
+    }
+    Ok(res)
+}
+
+/// Converts a one based (or negative, relative) obj index into a zero based one.
+fn resolve_index(token: &str, len: usize) -> anyhow::Result<usize> {
+    let index: isize = parse_value(Some(token))?;
+
+    let res = match index {
+        0 => bail!("obj indices start at 1"),
+        i if i > 0 => (i - 1) as usize,
+        i => {
+            let back = i.unsigned_abs();
+            if back > len {
+                bail!("relative index {} out of range", i);
+            }
+            len - back
+        }
+    };
+
+    if res >= len {
+        bail!("index {} out of range of {} elements", index, len);
+    }
+
+    Ok(res)
+}
+
+/// Parses one of `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+fn parse_face_vertex(token: &str, data: &ObjData) -> anyhow::Result<FaceVertex> {
+    let mut parts = token.split('/');
+
+    let position = resolve_index(parts.next().unwrap_or_default(), data.positions.len())?;
+
+    let optional = |part: Option<&str>, len| match part {
+        None | Some("") => Ok(None),
+        Some(part) => resolve_index(part, len).map(Some),
+    };
+
+    let texcoord = optional(parts.next(), data.texcoords.len())?;
+    let normal = optional(parts.next(), data.normals.len())?;
+
+    if parts.next().is_some() {
+        bail!("invalid face vertex {:?}", token);
+    }
+
+    Ok(FaceVertex {
+        position,
+        texcoord,
+        normal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+# a simple quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+
+g front
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+
+g back
+usemtl blue
+f -4//1 -2//1 \\
+  -3//1
+";
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let data = parse(QUAD, Path::new(""))?;
+
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.texcoords.len(), 4);
+        assert_eq!(data.normals.len(), 1);
+        assert_eq!(data.groups.len(), 2);
+
+        let front = &data.groups[0];
+        assert_eq!(front.name, "front");
+        assert_eq!(front.material.as_deref(), Some("red"));
+        assert_eq!(front.faces.len(), 2);
+        assert_eq!(
+            front.faces[1][2],
+            FaceVertex {
+                position: 3,
+                texcoord: Some(3),
+                normal: Some(0),
+            }
+        );
+
+        let back = &data.groups[1];
+        assert_eq!(back.name, "back");
+        assert_eq!(back.material.as_deref(), Some("blue"));
+        assert_eq!(back.faces.len(), 1);
+        let positions: Vec<_> = back.faces[0].iter().map(|v| v.position).collect();
+        assert_eq!(positions, [0, 2, 1]);
+        assert!(back.faces[0].iter().all(|v| v.texcoord.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for src in ["v 0 0", "v 0 0 0\nf 1 2 3", "v 0 0 0\nf 0 0 0", "vt x"] {
+            assert!(parse(src, Path::new("")).is_err(), "{:?} should fail", src);
+        }
+    }
+
+    #[test]
+    fn test_parse_mtl() -> anyhow::Result<()> {
+        let src = "
+newmtl glass
+Ni 1.5
+d 0.1
+
+newmtl lamp
+Ke 4 4 4
+map_Kd -bm 1.0 textures/lamp.png
+";
+        let materials = parse_mtl(src, Path::new("assets"))?;
+
+        assert_eq!(materials.len(), 2);
+
+        let glass = &materials["glass"];
+        assert_eq!(glass.ior, 1.5);
+        assert_eq!(glass.dissolve, 0.1);
+
+        let lamp = &materials["lamp"];
+        assert_eq!(lamp.emission, Color::new(4.0, 4.0, 4.0));
+        assert_eq!(
+            lamp.diffuse_map.as_deref(),
+            Some(Path::new("assets/textures/lamp.png"))
+        );
+
+        assert!(parse_mtl("Kd 1 1 1", Path::new("")).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::mesh::MeshBuffers;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        let res = match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("unknown property type {:?}", name),
+        };
+        Ok(res)
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Self::Scalar(name, _) | Self::List(name, _, _) => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+/// Reads the values of the body one after the other, regardless of the format.
+enum Body<'a> {
+    Ascii(&'a str),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    /// An upper bound of the rows of `element` the rest of the body can hold,
+    /// so a header can't make us allocate more than the file contains.
+    fn max_rows(&self, element: &Element) -> usize {
+        let (remaining, row_size) = match self {
+            // every ascii value takes at least one byte
+            Self::Ascii(text) => (text.len(), element.properties.len()),
+            Self::Binary { data, .. } => (
+                data.len(),
+                element
+                    .properties
+                    .iter()
+                    .map(|p| match p {
+                        Property::Scalar(_, ty) | Property::List(_, ty, _) => ty.size(),
+                    })
+                    .sum(),
+            ),
+        };
+
+        element.count.min(remaining / row_size.max(1))
+    }
+
+    fn next(&mut self, ty: Scalar) -> anyhow::Result<f64> {
+        match self {
+            Self::Ascii(text) => {
+                let trimmed = text.trim_start();
+                let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+                let (token, remaining) = trimmed.split_at(end);
+                *text = remaining;
+
+                if token.is_empty() {
+                    bail!("unexpected end of data");
+                }
+                token
+                    .parse()
+                    .with_context(|| format!("invalid value {:?}", token))
+            }
+            Self::Binary { data, big_endian } => {
+                let size = ty.size();
+                if data.len() < size {
+                    bail!("unexpected end of data");
+                }
+                let (bytes, remaining) = data.split_at(size);
+                *data = remaining;
+
+                let mut buf = [0u8; 8];
+                buf[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buf[..size].reverse();
+                }
+
+                let res = match ty {
+                    Scalar::I8 => buf[0] as i8 as f64,
+                    Scalar::U8 => buf[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buf),
+                };
+                Ok(res)
+            }
+        }
+    }
+}
+
+/// Reads an ascii or binary ply file.
+pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<MeshBuffers> {
+    let path = path.as_ref();
+    let data =
+        fs::read(path).with_context(|| format!("unable to read ply file {}", path.display()))?;
+
+    parse(&data).with_context(|| format!("unable to parse ply file {}", path.display()))
+}
+
+/// Parses the content of a ply file.
+///
+/// Only the `vertex` (x, y, z and optionally nx, ny, nz and red, green, blue)
+/// and `face` (vertex_indices) elements are used, all others are skipped.
+pub fn parse(data: &[u8]) -> anyhow::Result<MeshBuffers> {
+    let (header, body) = split_header(data)?;
+    let header = parse_header(header)?;
+
+    let mut body = match header.format {
+        Format::Ascii => {
+            Body::Ascii(std::str::from_utf8(body).context("the ascii body is not valid utf-8")?)
+        }
+        Format::BinaryLittleEndian => Body::Binary {
+            data: body,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            data: body,
+            big_endian: true,
+        },
+    };
+
+    let mut res = MeshBuffers::default();
+
+    for element in header.elements.iter() {
+        match element.name.as_str() {
+            "vertex" => read_vertices(element, &mut body, &mut res)?,
+            "face" => read_faces(element, &mut body, &mut res)?,
+            // rows without properties take no space, whatever their count
+            _ if element.properties.is_empty() => {}
+            _ => {
+                for _ in 0..element.count {
+                    read_row(element, &mut body, |_, _| Ok(()))?;
+                }
+            }
+        }
+    }
+
+    let len = res.positions.len();
+    if let Some(i) = res.indices.iter().flatten().find(|&&i| i >= len) {
+        bail!("face index {} out of range of {} vertices", i, len);
+    }
+
+    Ok(res)
+}
+
+/// Splits the file into the header and the body.
+fn split_header(data: &[u8]) -> anyhow::Result<(&str, &[u8])> {
+    const END: &[u8] = b"end_header";
+
+    if !data.starts_with(b"ply") {
+        bail!("missing ply magic number");
+    }
+
+    let pos = data
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| anyhow!("missing end_header"))?;
+
+    // the body starts after the end of the end_header line
+    let body = &data[pos + END.len()..];
+    let newline = body
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| anyhow!("missing body"))?;
+
+    let header = std::str::from_utf8(&data[..pos]).context("the header is not valid ascii")?;
+
+    Ok((header, &body[newline + 1..]))
+}
+
+fn parse_header(header: &str) -> anyhow::Result<Header> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    // skip the magic number
+    for (i, line) in header.lines().enumerate().skip(1) {
+        let mut tokens = line.split_whitespace();
+
+        let res: anyhow::Result<()> = (|| {
+            match tokens.next() {
+                Some("format") => {
+                    format = Some(match tokens.next() {
+                        Some("ascii") => Format::Ascii,
+                        Some("binary_little_endian") => Format::BinaryLittleEndian,
+                        Some("binary_big_endian") => Format::BinaryBigEndian,
+                        other => bail!("unknown format {:?}", other),
+                    });
+                }
+                Some("element") => {
+                    let name = tokens.next().ok_or_else(|| anyhow!("missing name"))?;
+                    let count = tokens.next().ok_or_else(|| anyhow!("missing count"))?;
+                    elements.push(Element {
+                        name: name.to_string(),
+                        count: count
+                            .parse()
+                            .with_context(|| format!("invalid count {:?}", count))?,
+                        properties: Vec::new(),
+                    });
+                }
+                Some("property") => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| anyhow!("property found before any element"))?;
+
+                    let mut next = || tokens.next().ok_or_else(|| anyhow!("incomplete property"));
+
+                    let property = match next()? {
+                        "list" => {
+                            let count = Scalar::parse(next()?)?;
+                            let item = Scalar::parse(next()?)?;
+                            Property::List(next()?.to_string(), count, item)
+                        }
+                        ty => Property::Scalar(next()?.to_string(), Scalar::parse(ty)?),
+                    };
+                    element.properties.push(property);
+                }
+                Some("comment") | Some("obj_info") | None => {}
+                Some(other) => bail!("unknown header keyword {:?}", other),
+            }
+            Ok(())
+        })();
+
+        res.with_context(|| format!("header line {}: {}", i + 1, line))?;
+    }
+
+    let format = format.ok_or_else(|| anyhow!("missing format"))?;
+
+    Ok(Header { format, elements })
+}
+
+/// Reads a single row of an element, calling `f` for every property and list value.
+fn read_row<F>(element: &Element, body: &mut Body<'_>, mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(&str, f64) -> anyhow::Result<()>,
+{
+    for property in element.properties.iter() {
+        match property {
+            Property::Scalar(name, ty) => f(name, body.next(*ty)?)?,
+            Property::List(name, count, item) => {
+                let count = body.next(*count)?;
+                if count < 0.0 {
+                    bail!("negative list length in {}", name);
+                }
+                for _ in 0..(count as usize) {
+                    f(name, body.next(*item)?)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_vertices(
+    element: &Element,
+    body: &mut Body<'_>,
+    res: &mut MeshBuffers,
+) -> anyhow::Result<()> {
+    let has = |names: &[&str]| {
+        names
+            .iter()
+            .all(|n| element.properties.iter().any(|p| p.name() == *n))
+    };
+
+    if !has(&["x", "y", "z"]) {
+        bail!("the vertex element needs x, y and z properties");
+    }
+    let normals = has(&["nx", "ny", "nz"]);
+    let colors = has(&["red", "green", "blue"]);
+
+    // integer colors are in the range of 0 to 255
+    let color_scale = match element.properties.iter().find(|p| p.name() == "red") {
+        Some(Property::Scalar(_, Scalar::F32 | Scalar::F64)) => 1.0,
+        _ => 1.0 / 255.0,
+    };
+
+    let capacity = body.max_rows(element);
+    res.positions.reserve(capacity);
+    let mut normal_buf = Vec::with_capacity(if normals { capacity } else { 0 });
+    let mut color_buf = Vec::with_capacity(if colors { capacity } else { 0 });
+
+    for row in 0..element.count {
+        let mut pos = [0.0; 3];
+        let mut normal = [0.0; 3];
+        let mut color = [0.0; 3];
+
+        read_row(element, body, |name, val| {
+            match name {
+                "x" => pos[0] = val,
+                "y" => pos[1] = val,
+                "z" => pos[2] = val,
+                "nx" => normal[0] = val,
+                "ny" => normal[1] = val,
+                "nz" => normal[2] = val,
+                "red" => color[0] = val * color_scale,
+                "green" => color[1] = val * color_scale,
+                "blue" => color[2] = val * color_scale,
+                _ => {}
+            }
+            Ok(())
+        })
+        .with_context(|| format!("vertex {}", row))?;
+
+        res.positions.push(pos.into());
+        if normals {
+            normal_buf.push(normal.into());
+        }
+        if colors {
+            color_buf.push(color.into());
+        }
+    }
+
+    res.normals = normals.then_some(normal_buf);
+    res.colors = colors.then_some(color_buf);
+
+    Ok(())
+}
+
+fn read_faces(element: &Element, body: &mut Body<'_>, res: &mut MeshBuffers) -> anyhow::Result<()> {
+    let indices = element
+        .properties
+        .iter()
+        .find(|p| matches!(p.name(), "vertex_indices" | "vertex_index"))
+        .ok_or_else(|| anyhow!("the face element needs a vertex_indices property"))?;
+
+    if !matches!(indices, Property::List(..)) {
+        bail!("vertex_indices has to be a list");
+    }
+    let indices = indices.name().to_string();
+
+    let mut polygon = Vec::new();
+
+    for row in 0..element.count {
+        polygon.clear();
+
+        read_row(element, body, |name, val| {
+            if name == indices {
+                if val < 0.0 {
+                    bail!("negative vertex index");
+                }
+                polygon.push(val as usize);
+            }
+            Ok(())
+        })
+        .with_context(|| format!("face {}", row))?;
+
+        if polygon.len() < 3 {
+            bail!("face {} has less than three vertices", row);
+        }
+
+        // Triangulate polygons as a fan around the first vertex.
+        for w in polygon[1..].windows(2) {
+            res.indices.push([polygon[0], w[0], w[1]]);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    #[test]
+    fn test_ascii() -> anyhow::Result<()> {
+        let mesh = read(Path::new(FIXTURES).join("quad_ascii.ply"))?;
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, [[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.normals.is_none());
+
+        let colors = mesh.colors.expect("the fixture has colors");
+        assert_eq!(colors[0], [1.0, 0.0, 0.0].into());
+        assert_eq!(colors[3], [1.0, 1.0, 1.0].into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary() -> anyhow::Result<()> {
+        let ascii = read(Path::new(FIXTURES).join("quad_ascii.ply"))?;
+
+        for file in ["quad_le.ply", "quad_be.ply"] {
+            let mesh = read(Path::new(FIXTURES).join(file))?;
+
+            assert_eq!(mesh.positions, ascii.positions, "{}", file);
+            assert_eq!(mesh.indices, ascii.indices, "{}", file);
+            assert_eq!(mesh.colors, ascii.colors, "{}", file);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skipped_elements() -> anyhow::Result<()> {
+        let data = b"ply\nformat ascii 1.0\n\
+            element marker 18446744073709551615\n\
+            element vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+            element face 1\nproperty list uchar int vertex_indices\n\
+            end_header\n0 0 0\n1 0 0\n0 1 0\n0 1\n3 0 1 2\n";
+
+        let mesh = parse(data)?;
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.indices, [[0, 1, 2]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        let cases: &[&[u8]] = &[
+            b"",
+            b"plx\nformat ascii 1.0\nend_header\n",
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n",
+            b"ply\nformat utf16 1.0\nend_header\n",
+            b"ply\nproperty float x\nend_header\n",
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n",
+            b"ply\nformat ascii 1.0\nelement vertex one\nend_header\n",
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n",
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0\n",
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n3 0 1 2\n",
+            // a count far beyond the data must not be allocated up front
+            b"ply\nformat ascii 1.0\nelement vertex 1000000000000\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n",
+            b"ply\nformat binary_little_endian 1.0\nelement vertex 1000000000000\nproperty float x\nproperty float y\nproperty float z\nend_header\n\0\0\0\0",
+        ];
+
+        for case in cases {
+            assert!(
+                parse(case).is_err(),
+                "{:?} should fail",
+                String::from_utf8_lossy(case)
+            );
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{mesh::MeshBuffers, ray::Point};
+
+const HEADER_LEN: usize = 80;
+const TRIANGLE_LEN: usize = 50;
+
+/// Reads an ascii or binary stl file.
+pub fn read<P: AsRef<Path>>(path: P) -> anyhow::Result<MeshBuffers> {
+    let path = path.as_ref();
+    let data =
+        fs::read(path).with_context(|| format!("unable to read stl file {}", path.display()))?;
+
+    parse(&data).with_context(|| format!("unable to parse stl file {}", path.display()))
+}
+
+/// Parses the content of a stl file.
+///
+/// Stl stores every triangle on its own, so identical vertices are merged
+/// to create an indexed mesh. The facet normals are ignored.
+pub fn parse(data: &[u8]) -> anyhow::Result<MeshBuffers> {
+    // Some exporters write binary files whose header starts with "solid",
+    // so the size is checked as well.
+    if data.starts_with(b"solid") && !is_binary(data) {
+        let src = std::str::from_utf8(data).context("the ascii stl is not valid utf-8")?;
+        parse_ascii(src)
+    } else {
+        parse_binary(data)
+    }
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    match triangle_count(data) {
+        Some(count) => data.len() == HEADER_LEN + 4 + count * TRIANGLE_LEN,
+        None => false,
+    }
+}
+
+fn triangle_count(data: &[u8]) -> Option<usize> {
+    let count = data.get(HEADER_LEN..HEADER_LEN + 4)?;
+    Some(u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+}
+
+/// Merges identical vertices into an indexed mesh.
+#[derive(Default)]
+struct Builder {
+    lookup: HashMap<[u64; 3], usize>,
+    mesh: MeshBuffers,
+}
+
+impl Builder {
+    fn vertex(&mut self, p: [f64; 3]) -> usize {
+        let positions = &mut self.mesh.positions;
+        // -0.0 and 0.0 are the same vertex
+        let key = p.map(|v| (v + 0.0).to_bits());
+
+        *self.lookup.entry(key).or_insert_with(|| {
+            positions.push(Point::from(p));
+            positions.len() - 1
+        })
+    }
+
+    fn triangle(&mut self, vertices: [[f64; 3]; 3]) {
+        let tri = vertices.map(|v| self.vertex(v));
+        self.mesh.indices.push(tri);
+    }
+}
+
+fn parse_binary(data: &[u8]) -> anyhow::Result<MeshBuffers> {
+    let count = triangle_count(data).ok_or_else(|| anyhow!("missing stl header"))?;
+
+    let expected = HEADER_LEN + 4 + count * TRIANGLE_LEN;
+    if data.len() < expected {
+        bail!(
+            "expected {} triangles ({} bytes) but the file only has {} bytes",
+            count,
+            expected,
+            data.len()
+        );
+    }
+
+    let mut builder = Builder::default();
+
+    for tri in data[HEADER_LEN + 4..expected].chunks_exact(TRIANGLE_LEN) {
+        let float = |i: usize| {
+            let b = &tri[i * 4..i * 4 + 4];
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+        };
+
+        // the first three floats are the normal, the last two bytes the attributes
+        let mut vertices = [[0.0; 3]; 3];
+        for (v, vertex) in vertices.iter_mut().enumerate() {
+            for (c, val) in vertex.iter_mut().enumerate() {
+                *val = float(3 + v * 3 + c);
+            }
+        }
+
+        builder.triangle(vertices);
+    }
+
+    Ok(builder.mesh)
+}
+
+fn parse_ascii(src: &str) -> anyhow::Result<MeshBuffers> {
+    let mut builder = Builder::default();
+    let mut vertices = Vec::with_capacity(3);
+
+    for (i, line) in src.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+
+        let res: anyhow::Result<()> = (|| {
+            match tokens.next() {
+                Some("vertex") => {
+                    let mut v = [0.0; 3];
+                    for val in v.iter_mut() {
+                        let token = tokens.next().ok_or_else(|| anyhow!("missing value"))?;
+                        *val = token
+                            .parse()
+                            .with_context(|| format!("invalid value {:?}", token))?;
+                    }
+                    vertices.push(v);
+                }
+                Some("facet") => vertices.clear(),
+                Some("endfacet") => {
+                    if vertices.len() != 3 {
+                        bail!("a facet needs exactly three vertices");
+                    }
+                    builder.triangle([vertices[0], vertices[1], vertices[2]]);
+                }
+                Some("solid" | "outer" | "endloop" | "endsolid") | None => {}
+                Some(other) => bail!("unknown keyword {:?}", other),
+            }
+            Ok(())
+        })();
+
+        res.with_context(|| format!("line {}: {}", i + 1, line))?;
+    }
+
+    Ok(builder.mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    #[test]
+    fn test_ascii_and_binary() -> anyhow::Result<()> {
+        let ascii = read(Path::new(FIXTURES).join("tetrahedron_ascii.stl"))?;
+        let binary = read(Path::new(FIXTURES).join("tetrahedron_binary.stl"))?;
+
+        for mesh in [&ascii, &binary] {
+            assert_eq!(mesh.positions.len(), 4);
+            assert_eq!(mesh.indices.len(), 4);
+            assert!(mesh.colors.is_none());
+        }
+
+        assert_eq!(ascii.positions, binary.positions);
+        assert_eq!(ascii.indices, binary.indices);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed() {
+        let cases: &[&[u8]] = &[
+            b"",
+            b"solid broken\nfacet normal 0 0 1\nouter loop\nvertex 0 0\n",
+            b"solid broken\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nendloop\nendfacet\n",
+            b"solid broken\nfacet normal 0 0 1\nouter loop\nvortex 0 0 0\n",
+        ];
+
+        for case in cases {
+            assert!(
+                parse(case).is_err(),
+                "{:?} should fail",
+                String::from_utf8_lossy(case)
+            );
+        }
+
+        // a binary header announcing more triangles than there are
+        let mut data = vec![0u8; HEADER_LEN];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[0u8; TRIANGLE_LEN]);
+        assert!(parse(&data).is_err());
+    }
+}
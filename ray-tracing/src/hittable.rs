@@ -1,5 +1,3 @@
-use itertools::{self, izip};
-
 use std::{
     ops::{Deref, DerefMut},
     sync::Arc,
@@ -8,8 +6,12 @@ use std::{
 use crate::{
     aabb::Aabb,
     degrees_to_radians,
+    description::{self, Describer, ObjectDescription},
     material::Material,
+    rand_range,
     ray::{Point, Ray, Vec3},
+    render::Color,
+    transform::{Mat4, Transform},
 };
 
 #[derive(Default)]
@@ -21,6 +23,8 @@ pub struct InnerHitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    /// The vertex color at the hit, for meshes with vertex colors.
+    pub color: Option<Color>,
 }
 
 #[derive(Default)]
@@ -31,13 +35,13 @@ impl Deref for HitRecord {
     type Target = InnerHitRecord;
 
     fn deref(&self) -> &Self::Target {
-        &*self.0
+        &self.0
     }
 }
 
 impl DerefMut for HitRecord {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.0
+        &mut self.0
     }
 }
 
@@ -57,10 +61,58 @@ impl HitRecord {
 pub trait Hittable: Send + Sync {
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb>;
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// A serializable description of the object.
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        description::not_describable(std::any::type_name::<Self>())
+    }
+
+    /// Whether the object emits light and supports sampling directions
+    /// towards it with [`Hittable::random`].
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// The probability density, with respect to the solid angle, of
+    /// [`Hittable::random`] returning `direction` from `origin`.
+    fn pdf_value(&self, _origin: &Point, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// A random direction from `origin` towards the object.
+    fn random(&self, _origin: &Point) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
 
 pub type HittableObject = Arc<dyn Hittable>;
 
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        (**self).bounding_box(time0, time1)
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        (**self).describe(d)
+    }
+
+    fn is_light(&self) -> bool {
+        (**self).is_light()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        (**self).random(origin)
+    }
+}
+
 pub struct HittableList {
     objects: Vec<HittableObject>,
 }
@@ -90,6 +142,30 @@ impl HittableList {
     pub fn add<HO: Hittable + 'static>(&mut self, object: HO) {
         self.objects.push(Arc::new(object))
     }
+
+    /// Add an already shared object without wrapping it again.
+    pub fn add_arc(&mut self, object: HittableObject) {
+        self.objects.push(object)
+    }
+
+    /// The objects of the list that are lights, see [`Hittable::is_light`].
+    ///
+    /// Only the objects directly in the list are checked, a light inside of a
+    /// nested list or bvh isn't found.
+    pub fn lights(&self) -> Self {
+        Self {
+            objects: self
+                .objects
+                .iter()
+                .filter(|obj| obj.is_light())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
 
 impl Hittable for HittableList {
@@ -108,7 +184,7 @@ impl Hittable for HittableList {
     }
 
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
-        if self.objects.len() == 0 {
+        if self.objects.is_empty() {
             return None;
         }
         let mut first_box = true;
@@ -128,6 +204,40 @@ impl Hittable for HittableList {
 
         Some(output)
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let objects = self
+            .objects
+            .iter()
+            .map(|obj| obj.describe(d))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(ObjectDescription::List { objects })
+    }
+
+    fn is_light(&self) -> bool {
+        !self.objects.is_empty() && self.objects.iter().all(|obj| obj.is_light())
+    }
+
+    /// Picks one of the objects with the same probability.
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|obj| weight * obj.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        let index = rand_range(0..self.objects.len());
+        self.objects[index].random(origin)
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct Translate<H: Hittable> {
@@ -158,75 +268,162 @@ where
 
         let mut rec = self.ptr.hit(&moved, t_min, t_max)?;
 
+        // the direction is unchanged, so are the normal and the side
         rec.p += self.offset;
-        let normal = rec.normal;
-        rec.set_face_normal(&moved, &normal);
 
         Some(rec)
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Translate {
+            offset: self.offset.into(),
+            object: Box::new(self.ptr.describe(d)?),
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.ptr.is_light()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        self.ptr.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        self.ptr.random(&(*origin - self.offset))
+    }
 }
 
-pub struct RotateY<H: Hittable> {
+/// A rotation around the coordinate axis `AXIS`, use [`RotateX`],
+/// [`RotateY`] or [`RotateZ`].
+pub struct AxisRotate<H: Hittable, const AXIS: usize> {
     ptr: H,
+    angle: f64,
     sin_theta: f64,
     cos_theta: f64,
     bbox: Option<Aabb>,
 }
 
-impl<H: Hittable> RotateY<H> {
+/// A rotation by an angle in degrees around the x axis.
+pub type RotateX<H> = AxisRotate<H, 0>;
+/// A rotation by an angle in degrees around the y axis.
+pub type RotateY<H> = AxisRotate<H, 1>;
+/// A rotation by an angle in degrees around the z axis.
+pub type RotateZ<H> = AxisRotate<H, 2>;
+
+impl<H: Hittable, const AXIS: usize> AxisRotate<H, AXIS> {
     pub fn new(p: H, angle: f64) -> Self {
         let rads = degrees_to_radians(angle);
         let sin_theta = rads.sin();
         let cos_theta = rads.cos();
 
-        let bbox = p.bounding_box(0.0, 1.0);
+        let mut axis = [0.0; 3];
+        axis[AXIS] = 1.0;
+        let bbox = p
+            .bounding_box(0.0, 1.0)
+            .map(|bbox| Mat4::rotation(axis.into(), angle).transform_box(&bbox));
 
         Self {
             ptr: p,
+            angle,
             sin_theta,
             cos_theta,
-            bbox: Self::calculate_bound(bbox, sin_theta, cos_theta),
+            bbox,
         }
     }
 
-    fn calculate_bound(bbox: Option<Aabb>, sin_theta: f64, cos_theta: f64) -> Option<Aabb> {
-        let bbox = bbox?;
-        let mut min = [f64::INFINITY; 3];
-        let mut max = [-f64::INFINITY; 3];
+    /// Rotates `v` in the plane orthogonal to the axis, by the angle if `sign`
+    /// is positive and back if it's negative.
+    fn rotate(&self, v: &Vec3, sign: f64) -> Vec3 {
+        // the axes spanning the plane, in the order of a counterclockwise turn
+        let (i, j) = ((AXIS + 1) % 3, (AXIS + 2) % 3);
+        let sin = sign * self.sin_theta;
+
+        let mut res = *v;
+        res.data_mut()[i] = self.cos_theta * v.data()[i] - sin * v.data()[j];
+        res.data_mut()[j] = sin * v.data()[i] + self.cos_theta * v.data()[j];
+        res
+    }
+}
+
+impl<H, const AXIS: usize> Hittable for AxisRotate<H, AXIS>
+where
+    H: Hittable,
+{
+    fn bounding_box(&self, _time0: f64, _time11: f64) -> Option<Aabb> {
+        self.bbox.clone()
+    }
 
-        let calc = |iv, bv| {
-            let iv = iv as f64;
-            iv * bv + (1.0 - iv) * bv
-        };
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let org = self.rotate(r.origin(), -1.0);
+        let dir = self.rotate(r.direction(), -1.0);
 
-        for i in 0..2 {
-            let x = calc(i, bbox.max().x());
-            let cosx = cos_theta * x;
-            let sinx = -sin_theta * x;
+        let rotated = Ray::with_time(org, dir, r.time());
 
-            for j in 0..2 {
-                let y = calc(j, bbox.max().y());
+        let mut rec = self.ptr.hit(&rotated, t_min, t_max)?;
 
-                for k in 0..2 {
-                    let z = calc(k, bbox.max().z());
+        // a rotation keeps the normal facing against the ray, so the side
+        // found by the object stays valid
+        rec.p = self.rotate(&rec.p, 1.0);
+        rec.normal = self.rotate(&rec.normal, 1.0);
 
-                    let newx = cosx + sin_theta * z;
-                    let newz = sinx + cos_theta * z;
+        Some(rec)
+    }
 
-                    let tester = [newx, y, newz];
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let angle = self.angle;
+        let object = Box::new(self.ptr.describe(d)?);
 
-                    for (min, max, tes) in izip!(&mut min, &mut max, tester) {
-                        *min = f64::min(*min, tes);
-                        *max = f64::max(*max, tes);
-                    }
-                }
-            }
+        Ok(match AXIS {
+            0 => ObjectDescription::RotateX { angle, object },
+            1 => ObjectDescription::RotateY { angle, object },
+            _ => ObjectDescription::RotateZ { angle, object },
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.ptr.is_light()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let origin = self.rotate(origin, -1.0);
+        self.ptr.pdf_value(&origin, &self.rotate(direction, -1.0))
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        let local = self.ptr.random(&self.rotate(origin, -1.0));
+        self.rotate(&local, 1.0)
+    }
+}
+
+/// A rotation by an angle in degrees around an arbitrary axis through the
+/// origin.
+pub struct Rotate<H: Hittable> {
+    ptr: H,
+    axis: Vec3,
+    angle: f64,
+    rotation: Transform,
+    bbox: Option<Aabb>,
+}
+
+impl<H: Hittable> Rotate<H> {
+    pub fn new(p: H, axis: Vec3, angle: f64) -> Self {
+        let rotation = Transform::rotation(axis, angle);
+        let bbox = p
+            .bounding_box(0.0, 1.0)
+            .map(|bbox| rotation.transform_box(&bbox));
+
+        Self {
+            ptr: p,
+            axis,
+            angle,
+            rotation,
+            bbox,
         }
-        Some(Aabb::new(min.into(), max.into()))
     }
 }
 
-impl<H> Hittable for RotateY<H>
+impl<H> Hittable for Rotate<H>
 where
     H: Hittable,
 {
@@ -235,40 +432,179 @@ where
     }
 
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let oorg = r.origin();
-        let odir = r.direction();
+        let rotated = self.rotation.inverse().transform_ray(r);
 
-        let calc = |data: &Vec3| {
-            let mut res = data.clone();
+        let mut rec = self.ptr.hit(&rotated, t_min, t_max)?;
+        self.rotation.transform_record(&mut rec);
 
-            res.data_mut()[0] = self.cos_theta * data.data()[0] - self.sin_theta * data.data()[2];
-            res.data_mut()[2] = self.sin_theta * data.data()[0] + self.cos_theta * data.data()[2];
+        Some(rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Rotate {
+            axis: self.axis.into(),
+            angle: self.angle,
+            object: Box::new(self.ptr.describe(d)?),
+        })
+    }
 
-            res
-        };
+    fn is_light(&self) -> bool {
+        self.ptr.is_light()
+    }
 
-        let org = calc(oorg);
-        let dir = calc(odir);
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        self.rotation.pdf_value(&self.ptr, origin, direction)
+    }
 
-        let rotated = Ray::with_time(org, dir, r.time());
+    fn random(&self, origin: &Point) -> Vec3 {
+        self.rotation.random(&self.ptr, origin)
+    }
+}
 
-        let mut rec = self.ptr.hit(&rotated, t_min, t_max)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{Lambertian, Mat},
+        objects::{rect, Cube, Sphere, Triangle},
+        transform::Transformed,
+    };
+
+    fn random_object(mat: &Mat) -> HittableObject {
+        let corner = Point::random_range(-5.0..5.0);
+        match rand_range(0..4) {
+            0 => Arc::new(Cube::new(
+                &corner,
+                &(corner + Vec3::random_range(0.1..3.0)),
+                mat.clone(),
+            )),
+            1 => Arc::new(Sphere::new(corner, rand_range(0.1..2.0), mat.clone())),
+            2 => Arc::new(Triangle::new(
+                [
+                    corner,
+                    Point::random_range(-5.0..5.0),
+                    Point::random_range(-5.0..5.0),
+                ],
+                mat.clone(),
+            )),
+            _ => Arc::new(rect::XZ::new(
+                mat.clone(),
+                (corner.x(), corner.x() + rand_range(0.1..3.0)),
+                (corner.z(), corner.z() + rand_range(0.1..3.0)),
+                corner.y(),
+            )),
+        }
+    }
 
-        let calc = |data: &Vec3| {
-            let mut res = data.clone();
+    /// Wraps `obj` and returns the transformation the wrapper applies.
+    fn random_wrapper(obj: HittableObject) -> (HittableObject, Transform) {
+        let angle = rand_range(-180.0..180.0);
+        match rand_range(0..6) {
+            0 => (
+                Arc::new(RotateX::new(obj, angle)),
+                Transform::rotation_x(angle),
+            ),
+            1 => (
+                Arc::new(RotateY::new(obj, angle)),
+                Transform::rotation_y(angle),
+            ),
+            2 => (
+                Arc::new(RotateZ::new(obj, angle)),
+                Transform::rotation_z(angle),
+            ),
+            3 => {
+                let axis = Vec3::random_unit_vector();
+                (
+                    Arc::new(Rotate::new(obj, axis, angle)),
+                    Transform::rotation(axis, angle),
+                )
+            }
+            4 => {
+                let offset = Vec3::random_range(-5.0..5.0);
+                (
+                    Arc::new(Translate::new(obj, offset)),
+                    Transform::translation(offset),
+                )
+            }
+            _ => {
+                let transform = Transform::scaling(Vec3::random_range(0.2..3.0))
+                    .then(&Transform::rotation(Vec3::random_unit_vector(), angle));
+                (Arc::new(Transformed::new(obj, transform)), transform)
+            }
+        }
+    }
 
-            res.data_mut()[0] = self.cos_theta * data.data()[0] + self.sin_theta * data.data()[2];
-            res.data_mut()[2] = -self.sin_theta * data.data()[0] + self.cos_theta * data.data()[2];
+    /// Every hit point has to lie inside of the bounding box, otherwise a
+    /// bvh would cull the object, and the wrappers have to keep the side of
+    /// the surface that was hit.
+    #[test]
+    fn test_hits_inside_bounds() {
+        let mat: Mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+
+        for case in 0..500 {
+            let base = random_object(&mat);
+            let mut obj = base.clone();
+            let mut transform = Transform::identity();
+            for _ in 0..rand_range(1..4) {
+                let (wrapped, t) = random_wrapper(obj);
+                obj = wrapped;
+                transform = transform.then(&t);
+            }
 
-            res
-        };
+            let bbox = obj.bounding_box(0.0, 1.0).unwrap();
+            let inside = |p: &Point| {
+                (0..3).all(|axis| {
+                    let v = p.data()[axis];
+                    bbox.min().data()[axis] - 1e-9 <= v && v <= bbox.max().data()[axis] + 1e-9
+                })
+            };
 
-        let p = calc(&rec.p);
-        let normal = calc(&rec.normal);
+            for _ in 0..100 {
+                // aim at the box so most of the rays hit
+                let target = Point::new(
+                    rand_range(bbox.min().x()..=bbox.max().x()),
+                    rand_range(bbox.min().y()..=bbox.max().y()),
+                    rand_range(bbox.min().z()..=bbox.max().z()),
+                );
+                let origin = target + 20.0 * Vec3::random_unit_vector();
+                let r = Ray::new(origin, target - origin);
+
+                if let Some(rec) = obj.hit(&r, 0.001, f64::INFINITY) {
+                    assert!(
+                        inside(&rec.p),
+                        "case {}: hit {:?} is outside of {:?}",
+                        case,
+                        rec.p,
+                        bbox
+                    );
+                    assert!(
+                        Vec3::dot(r.direction(), &rec.normal) <= 1e-9,
+                        "case {}: the normal {:?} faces along the ray",
+                        case,
+                        rec.normal
+                    );
+
+                    let unwrapped = transform.inverse().transform_ray(&r);
+                    if let Some(inner) = base.hit(&unwrapped, 0.001, f64::INFINITY) {
+                        assert_eq!(
+                            rec.front_face, inner.front_face,
+                            "case {}: the wrappers flipped the side",
+                            case
+                        );
+                    }
+                }
+            }
+        }
+    }
 
-        rec.p = p;
-        rec.set_face_normal(&rotated, &normal);
+    #[test]
+    fn test_rotate_off_center() {
+        let mat: Mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let cube = Cube::new(&Point::new(1.0, 1.0, 1.0), &Point::new(2.0, 2.0, 2.0), mat);
 
-        Some(rec)
+        let rotated = RotateY::new(cube, 90.0);
+        let bbox = rotated.bounding_box(0.0, 1.0).unwrap();
+        assert!((*bbox.min() - Point::new(1.0, 1.0, -2.0)).near_zero());
+        assert!((*bbox.max() - Point::new(2.0, 2.0, -1.0)).near_zero());
     }
 }
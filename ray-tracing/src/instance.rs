@@ -0,0 +1,194 @@
+use crate::{
+    aabb::Aabb,
+    description::{Describer, ObjectDescription},
+    hittable::{HitRecord, Hittable, HittableObject},
+    material::Mat,
+    ray::{Point, Ray, Vec3},
+    transform::{Mat4, Transform},
+};
+
+/// A placement of a shared object, usually a bvh over a mesh, with its own
+/// transformation and optionally its own material.
+///
+/// All the instances of an object only hold a reference to it, so placing it
+/// many times costs one copy in memory. Put the instances into a
+/// [`LinearBvh`](crate::bvh::LinearBvh) to get a two level hierarchy.
+pub struct Instance {
+    object: HittableObject,
+    transform: Transform,
+    material: Option<Mat>,
+}
+
+impl Instance {
+    /// Panics if the transformation isn't invertible.
+    pub fn new(object: HittableObject, transform: Mat4) -> Self {
+        let transform = Transform::new(transform)
+            .expect("the transformation of an instance has to be invertible");
+
+        Self::with_transform(object, transform)
+    }
+
+    pub fn with_transform(object: HittableObject, transform: Transform) -> Self {
+        Self {
+            object,
+            transform,
+            material: None,
+        }
+    }
+
+    /// Replaces the materials of the object with the given one.
+    pub fn with_material(object: HittableObject, transform: Mat4, material: Mat) -> Self {
+        Self {
+            material: Some(material),
+            ..Self::new(object, transform)
+        }
+    }
+
+    /// Get a clone of the instanced object.
+    pub fn object(&self) -> HittableObject {
+        self.object.clone()
+    }
+
+    /// Get a reference to the instance's transformation.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Set the material replacing the ones of the object, `None` keeps them.
+    pub fn set_material(&mut self, material: Option<Mat>) {
+        self.material = material;
+    }
+}
+
+impl Hittable for Instance {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = self.object.bounding_box(time0, time1)?;
+        Some(self.transform.transform_box(&bbox))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let local = self.transform.inverse().transform_ray(r);
+
+        let mut rec = self.object.hit(&local, t_min, t_max)?;
+        self.transform.transform_record(&mut rec);
+
+        if let Some(mat) = &self.material {
+            rec.mat = Some(mat.clone());
+        }
+
+        Some(rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Instance {
+            matrix: (*self.transform.matrix()).into(),
+            material: self
+                .material
+                .as_ref()
+                .map(|mat| d.material(&**mat))
+                .transpose()?,
+            object: d.shared(&self.object)?,
+        })
+    }
+
+    /// Only objects which are lights themselves can be sampled, so a
+    /// replaced material can only turn a light off.
+    fn is_light(&self) -> bool {
+        let emissive = self.material.as_ref().is_none_or(|mat| mat.is_emissive());
+        emissive && self.object.is_light()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        self.transform.pdf_value(&self.object, origin, direction)
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        self.transform.random(&self.object, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        bvh::LinearBvh,
+        hittable::HittableList,
+        material::{Lambertian, Metal},
+        objects::Sphere,
+        rand_range,
+        ray::{Point, Vec3},
+    };
+
+    #[test]
+    fn test_forest() {
+        let mat: Mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let tree: HittableObject = Arc::new(Sphere::new(Point::zeros(), 1.0, mat.clone()));
+
+        let mut instances = HittableList::new();
+        let mut expected = HittableList::new();
+        for _ in 0..10_000 {
+            let offset = Vec3::random_range(-100.0..100.0);
+            let scale = rand_range(0.1..0.5);
+            let transform = Mat4::translation(offset) * Mat4::scaling(Vec3::ones() * scale);
+
+            instances.add(Instance::new(tree.clone(), transform));
+            expected.add(Sphere::new(offset, scale, mat.clone()));
+        }
+
+        // the tree is only stored once
+        assert_eq!(Arc::strong_count(&tree), 10_001);
+
+        let forest = LinearBvh::new(&instances, 0.0, 1.0);
+        let expected = LinearBvh::new(&expected, 0.0, 1.0);
+
+        for _ in 0..500 {
+            let r = Ray::new(
+                Point::random_range(-120.0..120.0),
+                Vec3::random_unit_vector(),
+            );
+
+            let actual = forest.hit(&r, 0.001, f64::INFINITY);
+            let wanted = expected.hit(&r, 0.001, f64::INFINITY);
+            match (actual, wanted) {
+                (None, None) => {}
+                (Some(a), Some(e)) => {
+                    assert!((a.t - e.t).abs() < 1e-9);
+                    assert!((a.p - e.p).near_zero());
+                    assert!((a.normal - e.normal).near_zero());
+                    assert_eq!(a.front_face, e.front_face);
+                }
+                (a, e) => panic!("hit {} but expected {}", a.is_some(), e.is_some()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_uniform_scale() {
+        let mat: Mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let sphere: HittableObject = Arc::new(Sphere::new(Point::zeros(), 1.0, mat));
+
+        // an ellipsoid stretched along x
+        let metal: Mat = Arc::new(Metal::new([0.8, 0.8, 0.8].into(), 0.0));
+        let transform = Mat4::scaling([4.0, 1.0, 1.0].into());
+        let instance = Instance::with_material(sphere, transform, metal.clone());
+
+        let bbox = instance.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(bbox.min(), &Point::new(-4.0, -1.0, -1.0));
+        assert_eq!(bbox.max(), &Point::new(4.0, 1.0, 1.0));
+
+        let r = Ray::new(Point::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = instance.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).near_zero());
+        assert!(Arc::ptr_eq(rec.mat.as_ref().unwrap(), &metal));
+
+        // the normal of the ellipsoid at 45 degrees points mostly up
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        let r = Ray::new(Point::new(4.0 * s, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = instance.hit(&r, 0.001, f64::INFINITY).unwrap();
+        let expected = Vec3::new(s / 4.0, s, 0.0).unit_vector();
+        assert!((rec.normal - expected).near_zero());
+    }
+}
@@ -0,0 +1,741 @@
+use std::sync::Arc;
+
+use crate::{
+    background::Background,
+    hittable::{HitRecord, Hittable, HittableList},
+    light::Light,
+    material::Mat,
+    onb::{self, Onb},
+    rand_range,
+    ray::{Point, Ray, Vec3},
+    render::Color,
+};
+
+/// Everything an integrator gathers the light from.
+pub struct Scene<'a> {
+    pub world: &'a dyn Hittable,
+    /// The lights sampled directly, usually [`HittableList::lights`] of the
+    /// world.
+    pub lights: &'a HittableList,
+    /// The light arriving along rays missing the world, sampled together
+    /// with the lights if it is one.
+    pub background: &'a dyn Background,
+    /// The lights without a surface, only reached by shadow rays.
+    pub delta_lights: &'a [Arc<dyn Light>],
+    /// The maximal number of bounces of a ray.
+    pub max_depth: usize,
+}
+
+impl Scene<'_> {
+    /// The number of ways to pick a direction towards a light, the lights
+    /// and the background.
+    fn light_strategies(&self) -> usize {
+        usize::from(!self.lights.is_empty()) + usize::from(self.background.is_light())
+    }
+
+    /// The density of [`Self::light_random`] picking the given direction.
+    fn light_pdf(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let count = self.light_strategies();
+        if count == 0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        if !self.lights.is_empty() {
+            pdf += self.lights.pdf_value(origin, direction);
+        }
+        if self.background.is_light() {
+            pdf += self.background.pdf_value(direction);
+        }
+
+        pdf / count as f64
+    }
+
+    /// A direction from `origin` towards one of the lights or the
+    /// background, `None` without any.
+    fn light_random(&self, origin: &Point) -> Option<Vec3> {
+        match (self.lights.is_empty(), self.background.is_light()) {
+            (true, false) => None,
+            (false, false) => Some(self.lights.random(origin)),
+            (true, true) => Some(self.background.random()),
+            (false, true) => Some(if rand_range(0.0..1.0) < 0.5 {
+                self.lights.random(origin)
+            } else {
+                self.background.random()
+            }),
+        }
+    }
+
+    /// The light arriving along `r`, emitted at its hit `rec` or coming from
+    /// the background if it doesn't hit anything.
+    fn arriving(&self, r: &Ray, rec: Option<&HitRecord>) -> Color {
+        match rec {
+            Some(rec) => material(rec).emitted(r, rec),
+            None => self.background.value(r.direction()),
+        }
+    }
+}
+
+/// A light transport algorithm, gives the color of a ray shot from the
+/// camera.
+pub trait Integrator: Send + Sync {
+    /// The light arriving at the origin of `r` from its direction.
+    fn ray_color(&self, r: &Ray, scene: &Scene<'_>) -> Color;
+}
+
+fn material(rec: &HitRecord) -> &Mat {
+    rec.mat
+        .as_ref()
+        .expect("at this point the rec should have a inner material")
+}
+
+/// How the light found by sampling the lights and by sampling the bsdf is
+/// weighted (multiple importance sampling).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mis {
+    /// Weights by the densities, `a / (a + b)`.
+    #[default]
+    Balance,
+    /// Weights by the squared densities, `a² / (a² + b²)`. Favours the better
+    /// strategy more strongly, which helps with both small bright lights and
+    /// large soft ones.
+    Power,
+}
+
+impl Mis {
+    /// The weight of a sample taken with the density `a` when the same
+    /// direction could also have been taken with the density `b`.
+    pub fn weight(self, a: f64, b: f64) -> f64 {
+        let (a, b) = match self {
+            Self::Balance => (a, b),
+            Self::Power => (a * a, b * b),
+        };
+
+        if a + b > 0.0 {
+            a / (a + b)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The light arriving along `r` from its hit `rec` or the background.
+/// `bsdf_pdf` is the density the scattered ray `r` was picked with, the
+/// light is then weighted against finding it by sampling the lights. `None`
+/// for rays which can't be found that way, like camera rays and specular
+/// bounces.
+fn emission(
+    r: &Ray,
+    rec: Option<&HitRecord>,
+    scene: &Scene<'_>,
+    bsdf_pdf: Option<f64>,
+    mis: Mis,
+) -> Color {
+    let emitted = scene.arriving(r, rec);
+
+    match bsdf_pdf {
+        Some(bsdf_pdf) if emitted != Color::zeros() => {
+            let light_pdf = scene.light_pdf(r.origin(), r.direction());
+            mis.weight(bsdf_pdf, light_pdf) * emitted
+        }
+        _ => emitted,
+    }
+}
+
+/// Sends a shadow ray from the hit `rec` of `r` towards one of the lights or
+/// the background, weighted against finding the light by the scattered ray.
+fn sample_light(r: &Ray, rec: &HitRecord, scene: &Scene<'_>, mis: Mis) -> Color {
+    let direction = match scene.light_random(&rec.p) {
+        Some(direction) => direction,
+        None => return Color::zeros(),
+    };
+
+    let mat = material(rec);
+    let to_light = Ray::with_time(rec.p, direction, r.time());
+    let light_pdf = scene.light_pdf(to_light.origin(), to_light.direction());
+    if light_pdf <= 0.0 {
+        return Color::zeros();
+    }
+
+    let bsdf_pdf = mat.pdf(r, rec, &to_light);
+    if bsdf_pdf <= 0.0 {
+        return Color::zeros();
+    }
+
+    let light_rec = scene.world.hit(&to_light, 0.001, f64::INFINITY);
+    let le = scene.arriving(&to_light, light_rec.as_ref());
+    let weight = mis.weight(light_pdf, bsdf_pdf);
+
+    (weight / light_pdf) * mat.eval(r, rec, &to_light) * le
+}
+
+/// Sends a shadow ray from the hit `rec` of `r` to each of the lights
+/// without a surface.
+fn sample_delta_lights(r: &Ray, rec: &HitRecord, scene: &Scene<'_>) -> Color {
+    let mat = material(rec);
+
+    let mut color = Color::zeros();
+    for light in scene.delta_lights {
+        let sample = match light.sample(&rec.p) {
+            Some(sample) => sample,
+            None => continue,
+        };
+
+        let to_light = Ray::with_time(rec.p, sample.direction, r.time());
+        if scene
+            .world
+            .hit(&to_light, 0.001, sample.distance - 0.001)
+            .is_none()
+        {
+            color += mat.eval(r, rec, &to_light) * sample.irradiance;
+        }
+    }
+
+    color
+}
+
+/// The number of bounces after which [`PathTracer`] starts to end paths at
+/// random.
+pub const ROULETTE_DEPTH: usize = 5;
+
+/// The path tracer.
+///
+/// Every diffuse hit also sends a shadow ray to one of the lights of the
+/// scene, combined with the scattered ray hitting a light by multiple
+/// importance sampling, and one to each light without a surface. Without
+/// any lights only the scattered rays find light.
+///
+/// Paths are followed in a loop, the light found along a path is scaled by
+/// its throughput, the product of the sample weights so far. After the
+/// roulette depth paths are ended at random (Russian roulette), the darker
+/// the throughput the more likely. Surviving paths are brightened by the
+/// same factor, so the image stays the same on average.
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer {
+    roulette_depth: usize,
+    mis: Mis,
+}
+
+impl PathTracer {
+    /// `roulette_depth` is the number of bounces every path makes unless it
+    /// escapes, `usize::MAX` turns the roulette off.
+    pub fn new(roulette_depth: usize) -> Self {
+        Self::with_mis(roulette_depth, Mis::default())
+    }
+
+    pub fn with_mis(roulette_depth: usize, mis: Mis) -> Self {
+        Self {
+            roulette_depth,
+            mis,
+        }
+    }
+
+    /// Get the number of bounces before paths are ended at random.
+    pub fn roulette_depth(&self) -> usize {
+        self.roulette_depth
+    }
+
+    /// Get the weighting of the light and the bsdf sampling.
+    pub fn mis(&self) -> Mis {
+        self.mis
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new(ROULETTE_DEPTH)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn ray_color(&self, r: &Ray, scene: &Scene<'_>) -> Color {
+        let mut color = Color::zeros();
+        let mut throughput = Color::ones();
+        let mut ray = *r;
+        // the density the current ray was picked with, see `emission`
+        let mut bsdf_pdf = None;
+
+        // No more light is gathered after the bounce limit.
+        for depth in 0..scene.max_depth {
+            let rec = scene.world.hit(&ray, 0.001, f64::INFINITY);
+            color += throughput * emission(&ray, rec.as_ref(), scene, bsdf_pdf, self.mis);
+
+            // If the ray hits nothing, only the background was added.
+            let rec = match rec {
+                Some(rec) => rec,
+                None => break,
+            };
+
+            let sample = match material(&rec).sample(&ray, &rec) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+            if sample.delta {
+                bsdf_pdf = None;
+            } else {
+                color += throughput * sample_light(&ray, &rec, scene, self.mis);
+                color += throughput * sample_delta_lights(&ray, &rec, scene);
+                bsdf_pdf = Some(sample.pdf);
+            }
+
+            throughput = throughput * sample.weight;
+            ray = sample.ray;
+
+            if depth + 1 >= self.roulette_depth {
+                let survival =
+                    f64::min(throughput.x().max(throughput.y()).max(throughput.z()), 1.0);
+                if rand_range(0.0..1.0) >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        color
+    }
+}
+
+/// Only gathers the light arriving directly from the emitting objects and the
+/// background after a single diffuse bounce, specular bounces are followed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectLighting {
+    mis: Mis,
+}
+
+impl DirectLighting {
+    pub fn new(mis: Mis) -> Self {
+        Self { mis }
+    }
+
+    /// Get the weighting of the light and the bsdf sampling.
+    pub fn mis(&self) -> Mis {
+        self.mis
+    }
+
+    fn trace(&self, r: &Ray, scene: &Scene<'_>, depth: usize) -> Color {
+        if depth == 0 {
+            return Color::zeros();
+        }
+
+        let rec = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => return scene.background.value(r.direction()),
+        };
+
+        let emitted = emission(r, Some(&rec), scene, None, self.mis);
+
+        let sample = match material(&rec).sample(r, &rec) {
+            Some(sample) => sample,
+            None => return emitted,
+        };
+
+        if sample.delta {
+            return emitted + sample.weight * self.trace(&sample.ray, scene, depth - 1);
+        }
+
+        let direct = sample_light(r, &rec, scene, self.mis) + sample_delta_lights(r, &rec, scene);
+
+        // the light found by the scattered ray
+        let found = scene.world.hit(&sample.ray, 0.001, f64::INFINITY);
+        let found = emission(
+            &sample.ray,
+            found.as_ref(),
+            scene,
+            Some(sample.pdf),
+            self.mis,
+        );
+
+        emitted + direct + sample.weight * found
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn ray_color(&self, r: &Ray, scene: &Scene<'_>) -> Color {
+        self.trace(r, scene, scene.max_depth)
+    }
+}
+
+/// Shades the first hit white if a random ray leaving it doesn't hit anything
+/// within the given distance and black otherwise. Averaged over the samples
+/// this gives the part of the sky visible from the hit.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+
+    /// Get the distance within which objects block the sky.
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self::new(f64::INFINITY)
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn ray_color(&self, r: &Ray, scene: &Scene<'_>) -> Color {
+        let rec = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => return Color::ones(),
+        };
+
+        let direction = Onb::from_w(&rec.normal).local(&onb::random_cosine_direction());
+        let ray = Ray::with_time(rec.p, direction, r.time());
+
+        match scene.world.hit(&ray, 0.001, self.distance) {
+            Some(_) => Color::zeros(),
+            None => Color::ones(),
+        }
+    }
+}
+
+/// Shows a property of the first hit instead of the light, rays missing the
+/// world are black.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    /// The outward normal, mapped from [-1, 1] to [0, 1] per axis.
+    Normals,
+    /// The texture coordinates as red and green.
+    Uv,
+    /// Green on the outside and red on the inside of an object.
+    FrontFace,
+}
+
+impl Integrator for DebugView {
+    fn ray_color(&self, r: &Ray, scene: &Scene<'_>) -> Color {
+        let rec = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => return Color::zeros(),
+        };
+
+        match self {
+            Self::Normals => {
+                let outward = if rec.front_face {
+                    rec.normal
+                } else {
+                    -rec.normal
+                };
+                0.5 * (outward + Color::ones())
+            }
+            Self::Uv => Color::new(rec.u, rec.v, 0.0),
+            Self::FrontFace if rec.front_face => Color::new(0.0, 1.0, 0.0),
+            Self::FrontFace => Color::new(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    use super::*;
+    use crate::{
+        environment::EnvironmentMap,
+        hittable::{HittableObject, RotateX},
+        light::{DirectionalLight, PointLight},
+        material::{DiffuseLight, Lambertian, Metal},
+        objects::{rect, Sphere},
+        ray::{Point, Vec3},
+        transform::{Transform, Transformed},
+    };
+
+    fn scene<'a>(world: &'a HittableList, background: &'a Color) -> Scene<'a> {
+        Scene {
+            world,
+            // neither of the tested integrators samples the lights
+            lights: world,
+            background,
+            delta_lights: &[],
+            max_depth: 10,
+        }
+    }
+
+    /// A white floor with a small sphere standing on it.
+    fn floor_and_sphere() -> HittableList {
+        let white = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut world = HittableList::new();
+        world.add(rect::XZ::new(
+            white.clone(),
+            (-10.0, 10.0),
+            (-10.0, 10.0),
+            0.0,
+        ));
+        world.add(Sphere::new(Point::new(-1.0, 0.5, 0.0), 0.5, white));
+        world
+    }
+
+    /// The mean color of many paths along `r`.
+    fn mean(integrator: &dyn Integrator, scene: &Scene, r: &Ray) -> Color {
+        let n = 50_000;
+        let sum = (0..n)
+            .map(|_| integrator.ray_color(r, scene))
+            .fold(Color::zeros(), |acc, c| acc + c);
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_roulette() {
+        // light bounces many times between a floor and a ceiling before it
+        // escapes to the sides
+        let mat = Arc::new(Lambertian::new([0.7, 0.7, 0.7].into()));
+        let mut world = HittableList::new();
+        for k in [0.0, 1.0] {
+            world.add(rect::XZ::new(mat.clone(), (-5.0, 5.0), (-5.0, 5.0), k));
+        }
+        let none = HittableList::new();
+        let scene = Scene {
+            world: &world,
+            lights: &none,
+            background: &Color::ones(),
+            delta_lights: &[],
+            max_depth: 50,
+        };
+
+        let r = Ray::new(Point::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mean = |integrator: PathTracer| {
+            let n = 20_000;
+            let sum: f64 = (0..n).map(|_| integrator.ray_color(&r, &scene).x()).sum();
+            sum / n as f64
+        };
+
+        let full = mean(PathTracer::new(usize::MAX));
+        let roulette = mean(PathTracer::new(0));
+        assert!(
+            (full - roulette).abs() < 0.03 * full,
+            "the roulette changed the mean from {} to {}",
+            full,
+            roulette
+        );
+    }
+
+    #[test]
+    fn test_mis_unbiased() {
+        let mut world = floor_and_sphere();
+
+        // a ceiling light turned from xy into xz and a squashed sphere
+        let light = DiffuseLight::new([4.0, 4.0, 4.0].into());
+        let ceiling = rect::XY::new(light, (-1.5, 1.5), (-1.5, 1.5), -3.0);
+        world.add(RotateX::new(ceiling, 90.0));
+        let light = Arc::new(DiffuseLight::new([2.0, 1.0, 0.5].into()));
+        let transform = Transform::scaling([1.0, 0.5, 1.0].into())
+            .then(&Transform::translation([2.0, 1.0, 0.0].into()));
+        world.add(Transformed::new(
+            Sphere::new(Point::zeros(), 1.0, light),
+            transform,
+        ));
+        let metal = Arc::new(Metal::new([0.8, 0.8, 0.8].into(), 0.4));
+        world.add(Sphere::new(Point::new(0.5, 0.5, -1.5), 0.5, metal));
+
+        let lights = world.lights();
+        assert_eq!(lights.objects().len(), 2);
+
+        let none = HittableList::new();
+        let black = Color::zeros();
+        let scene = |lights| Scene {
+            world: &world,
+            lights,
+            background: &black,
+            delta_lights: &[],
+            max_depth: 10,
+        };
+
+        let origin = Point::new(0.0, 2.0, 5.0);
+        for target in [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(0.5, 0.8, -1.2),
+        ] {
+            let r = Ray::new(origin, target - origin);
+
+            let plain = mean(&PathTracer::default(), &scene(&none), &r);
+            for mis in [Mis::Balance, Mis::Power] {
+                let integrator = PathTracer::with_mis(ROULETTE_DEPTH, mis);
+                let sampled = mean(&integrator, &scene(&lights), &r);
+                for (p, s) in plain.data().iter().zip(sampled.data()) {
+                    assert!(
+                        (p - s).abs() < 0.05 * p,
+                        "{:?} sampling the lights gives {:?} instead of {:?}",
+                        mis,
+                        sampled,
+                        plain
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_environment_unbiased() {
+        /// The environment without being sampled as a light.
+        struct Unsampled(EnvironmentMap);
+
+        impl Background for Unsampled {
+            fn value(&self, direction: &Vec3) -> Color {
+                self.0.value(direction)
+            }
+        }
+
+        let (width, height) = (8, 4);
+        let mut pixels = vec![Color::new(0.2, 0.3, 0.5); width * height];
+        pixels[width + 3] = Color::new(20.0, 16.0, 12.0);
+        let env = || {
+            let mut env = EnvironmentMap::new(width, height, pixels.clone());
+            env.set_rotation(40.0);
+            env
+        };
+
+        let world = floor_and_sphere();
+        let none = HittableList::new();
+        let scene = |background| Scene {
+            world: &world,
+            lights: &none,
+            background,
+            delta_lights: &[],
+            max_depth: 10,
+        };
+
+        let (sampled, unsampled) = (env(), Unsampled(env()));
+        let origin = Point::new(0.0, 2.0, 5.0);
+        for target in [Point::new(0.0, 0.0, 0.0), Point::new(-1.0, 0.5, 0.0)] {
+            let r = Ray::new(origin, target - origin);
+
+            let plain = mean(&PathTracer::default(), &scene(&unsampled), &r);
+            for mis in [Mis::Balance, Mis::Power] {
+                let integrator = PathTracer::with_mis(ROULETTE_DEPTH, mis);
+                let sampled = mean(&integrator, &scene(&sampled), &r);
+                for (p, s) in plain.data().iter().zip(sampled.data()) {
+                    assert!(
+                        (p - s).abs() < 0.05 * p,
+                        "sampling the environment with {:?} changed {:?} to {:?}",
+                        mis,
+                        plain,
+                        sampled
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta_lights() {
+        let white = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let floor: HittableObject = Arc::new(rect::XZ::new(
+            white.clone(),
+            (-10.0, 10.0),
+            (-10.0, 10.0),
+            0.0,
+        ));
+        let mut world = HittableList::new();
+        world.add_arc(floor.clone());
+        // a sphere blocks the point light
+        let mut blocked = HittableList::new();
+        blocked.add_arc(floor);
+        blocked.add(Sphere::new(Point::new(0.0, 1.0, 0.0), 0.5, white));
+
+        let delta_lights: Vec<Arc<dyn Light>> = vec![
+            Arc::new(PointLight::new(
+                Point::new(0.0, 2.0, 0.0),
+                Color::new(4.0, 4.0, 4.0),
+            )),
+            Arc::new(DirectionalLight::new(
+                Vec3::new(-1.0, -1.0, 0.0),
+                Color::new(1.0, 2.0, 3.0),
+            )),
+        ];
+        let none = HittableList::new();
+        let black = Color::zeros();
+        let scene = |world| Scene {
+            world,
+            lights: &none,
+            background: &black,
+            delta_lights: &delta_lights,
+            // the bounces after the first hit don't find anything
+            max_depth: 2,
+        };
+
+        // the albedo over PI times the irradiance and the cosine
+        let point = 0.5 / PI * Color::new(1.0, 1.0, 1.0);
+        let directional = 0.5 / PI * FRAC_1_SQRT_2 * Color::new(1.0, 2.0, 3.0);
+
+        let r = Ray::new(Point::new(0.0, 1.0, 3.0), Vec3::new(0.0, -1.0, -3.0));
+        let integrators: [&dyn Integrator; 2] =
+            [&PathTracer::default(), &DirectLighting::default()];
+        for integrator in integrators {
+            let color = integrator.ray_color(&r, &scene(&world));
+            assert!((color - point - directional).near_zero(), "{:?}", color);
+        }
+
+        for integrator in integrators {
+            let color = integrator.ray_color(&r, &scene(&blocked));
+            assert!((color - directional).near_zero(), "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn test_ambient_occlusion() {
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut world = HittableList::new();
+        world.add(rect::XZ::new(
+            mat.clone(),
+            (-10.0, 10.0),
+            (-10.0, 10.0),
+            0.0,
+        ));
+
+        let black = Color::zeros();
+        let down = Ray::new(Point::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let ao = AmbientOcclusion::default();
+        for _ in 0..100 {
+            assert_eq!(ao.ray_color(&down, &scene(&world, &black)), Color::ones());
+        }
+
+        // a roof only blocks the sky within the distance
+        world.add(rect::XZ::new(
+            mat,
+            (-1000.0, 1000.0),
+            (-1000.0, 1000.0),
+            2.0,
+        ));
+        for _ in 0..100 {
+            assert_eq!(ao.ray_color(&down, &scene(&world, &black)), Color::zeros());
+        }
+
+        let ao = AmbientOcclusion::new(1.0);
+        for _ in 0..100 {
+            assert_eq!(ao.ray_color(&down, &scene(&world, &black)), Color::ones());
+        }
+    }
+
+    #[test]
+    fn test_debug_view() {
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let mut world = HittableList::new();
+        world.add(Sphere::new(Point::zeros(), 1.0, mat));
+
+        let outside = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let inside = Ray::new(Point::zeros(), Vec3::new(0.0, 0.0, -1.0));
+        let black = Color::zeros();
+        let scene = scene(&world, &black);
+
+        let normal = DebugView::Normals.ray_color(&outside, &scene);
+        assert!((normal - Color::new(0.5, 0.5, 1.0)).near_zero());
+        let normal = DebugView::Normals.ray_color(&inside, &scene);
+        assert!((normal - Color::new(0.5, 0.5, 0.0)).near_zero());
+
+        let front = DebugView::FrontFace;
+        assert_eq!(front.ray_color(&outside, &scene), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(front.ray_color(&inside, &scene), Color::new(1.0, 0.0, 0.0));
+
+        let miss = Ray::new(Point::new(0.0, 5.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(DebugView::Uv.ray_color(&miss, &scene), Color::zeros());
+    }
+}
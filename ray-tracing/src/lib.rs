@@ -1,15 +1,28 @@
 pub mod render;
 
 pub mod aabb;
+pub mod animation;
+pub mod background;
 pub mod bvh;
 
 pub mod camera;
+pub mod description;
+pub mod distribution;
+pub mod environment;
 pub mod hittable;
+pub mod instance;
+pub mod integrator;
+pub mod light;
 pub mod material;
 pub mod medium;
+pub mod mesh;
 pub mod objects;
+pub mod onb;
+pub mod profile;
 pub mod ray;
+pub mod sky;
 pub mod texture;
+pub mod transform;
 
 mod helpers;
 mod perlin;
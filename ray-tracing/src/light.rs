@@ -0,0 +1,244 @@
+//! Lights without a surface, which are only reached by shadow rays.
+//!
+//! Point, spot and directional lights send all their light from a single
+//! point or along a single direction, so a scattered ray never finds them.
+//! Instead every diffuse hit asks each of them for the light it receives.
+
+use std::sync::Arc;
+
+use crate::{
+    clamp, degrees_to_radians,
+    description::{self, LightDescription},
+    ray::{Point, Vec3},
+    render::Color,
+};
+
+/// The light a point receives from a [`Light`].
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// The unit direction from the point towards the light.
+    pub direction: Vec3,
+    /// The distance to the light, infinite for directional lights.
+    pub distance: f64,
+    /// The irradiance arriving at a surface facing the light.
+    pub irradiance: Color,
+}
+
+pub trait Light: Send + Sync {
+    /// The light arriving at `p`, `None` if `p` isn't lit at all.
+    fn sample(&self, p: &Point) -> Option<LightSample>;
+
+    /// A serializable description of the light.
+    fn describe(&self) -> anyhow::Result<LightDescription> {
+        description::not_describable(std::any::type_name::<Self>())
+    }
+}
+
+impl<L: Light + ?Sized> Light for Arc<L> {
+    fn sample(&self, p: &Point) -> Option<LightSample> {
+        (**self).sample(p)
+    }
+
+    fn describe(&self) -> anyhow::Result<LightDescription> {
+        (**self).describe()
+    }
+}
+
+/// Shines equally in all directions, falling off with the squared distance.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    position: Point,
+    intensity: Color,
+}
+
+impl PointLight {
+    /// The `intensity` is the irradiance at a distance of one.
+    pub fn new(position: Point, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+/// The unit direction and the distance from `p` to `position`, and the falloff
+/// with the squared distance.
+fn towards(p: &Point, position: &Point) -> (Vec3, f64, f64) {
+    let to_light = *position - *p;
+    let distance_squared = to_light.length_squared();
+    let distance = distance_squared.sqrt();
+
+    (to_light / distance, distance, 1.0 / distance_squared)
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: &Point) -> Option<LightSample> {
+        let (direction, distance, falloff) = towards(p, &self.position);
+
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: falloff * self.intensity,
+        })
+    }
+
+    fn describe(&self) -> anyhow::Result<LightDescription> {
+        Ok(LightDescription::Point {
+            position: self.position.into(),
+            intensity: self.intensity.into(),
+        })
+    }
+}
+
+/// A point light only shining into a cone.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    position: Point,
+    direction: Vec3,
+    intensity: Color,
+    /// In degrees from the axis.
+    cone_angle: f64,
+    /// In degrees from the axis.
+    falloff_angle: f64,
+}
+
+impl SpotLight {
+    /// The light is at its full `intensity` within `falloff_angle` of the
+    /// axis and fades out smoothly towards `cone_angle`, both in degrees.
+    pub fn new(
+        position: Point,
+        direction: Vec3,
+        intensity: Color,
+        cone_angle: f64,
+        falloff_angle: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction,
+            intensity,
+            cone_angle,
+            falloff_angle: falloff_angle.min(cone_angle),
+        }
+    }
+}
+
+/// The fraction of the light sent at an angle with the cosine `cos_theta` to
+/// the axis of a cone, full within `falloff_angle` and fading out smoothly
+/// towards `cone_angle`, both in degrees.
+pub(crate) fn cone_falloff(cos_theta: f64, cone_angle: f64, falloff_angle: f64) -> f64 {
+    let cos_cone = degrees_to_radians(cone_angle).cos();
+    let cos_falloff = degrees_to_radians(falloff_angle).cos();
+
+    if cos_theta >= cos_falloff {
+        return 1.0;
+    }
+
+    // smoothstep between the edge of the cone and the full intensity
+    let t = clamp((cos_theta - cos_cone) / (cos_falloff - cos_cone), 0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: &Point) -> Option<LightSample> {
+        let (direction, distance, falloff) = towards(p, &self.position);
+
+        let cos_theta = Vec3::dot(&-direction, &self.direction.unit_vector());
+        let cone = cone_falloff(cos_theta, self.cone_angle, self.falloff_angle);
+        if cone <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: (cone * falloff) * self.intensity,
+        })
+    }
+
+    fn describe(&self) -> anyhow::Result<LightDescription> {
+        Ok(LightDescription::Spot {
+            position: self.position.into(),
+            direction: self.direction.into(),
+            intensity: self.intensity.into(),
+            cone_angle: self.cone_angle,
+            falloff_angle: self.falloff_angle,
+        })
+    }
+}
+
+/// Parallel light from far away, like the sun.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// `direction` is the way the light travels.
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction,
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: &Point) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction.unit_vector(),
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+
+    fn describe(&self) -> anyhow::Result<LightDescription> {
+        Ok(LightDescription::Directional {
+            direction: self.direction.into(),
+            irradiance: self.irradiance.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_falloff() {
+        let light = PointLight::new(Point::new(0.0, 2.0, 0.0), Color::new(4.0, 8.0, 4.0));
+        let sample = light.sample(&Point::zeros()).unwrap();
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.irradiance, Color::new(1.0, 2.0, 1.0));
+
+        let spot = SpotLight::new(
+            Point::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -2.0, 0.0),
+            Color::ones(),
+            45.0,
+            30.0,
+        );
+        let at = |x: f64| {
+            spot.sample(&Point::new(x, 0.0, 0.0))
+                .map(|s| s.irradiance.x())
+        };
+
+        // full within the falloff angle, apart from the distance
+        assert_eq!(at(0.0), Some(1.0));
+        let inner = degrees_to_radians(29.0).tan();
+        assert!((at(inner).unwrap() - 1.0 / (1.0 + inner * inner)).abs() < 1e-9);
+
+        // fading between the angles
+        let middle = at(degrees_to_radians(38.0).tan()).unwrap();
+        assert!(0.0 < middle && middle < 0.5);
+
+        assert_eq!(at(degrees_to_radians(46.0).tan()), None);
+        assert_eq!(spot.sample(&Point::new(0.0, 2.0, 0.0)).map(|_| ()), None);
+
+        let sun = DirectionalLight::new(Vec3::new(0.0, -3.0, 0.0), Color::ones());
+        let sample = sun.sample(&Point::new(5.0, 0.0, 1.0)).unwrap();
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, f64::INFINITY);
+    }
+}
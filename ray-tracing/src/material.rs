@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
+    description::{self, Describer, MaterialDescription},
     hittable::HitRecord,
-    ray::{Point, Ray, Vec3},
+    onb::{self, Onb},
+    profile::{Profile, Sides},
+    ray::{Ray, Vec3},
     render::Color,
     rtweekend,
     texture::{SolidColor, Texture},
@@ -10,12 +13,105 @@ use crate::{
 
 pub type Mat = Arc<dyn Material>;
 
+/// A scattered ray picked by [`Material::sample`].
+pub struct BsdfSample {
+    pub ray: Ray,
+    /// The factor of the light arriving along `ray`, [`Material::eval`]
+    /// divided by the density `pdf`.
+    pub weight: Color,
+    /// The density, with respect to the solid angle, of the direction of
+    /// `ray`. Meaningless for delta samples.
+    pub pdf: f64,
+    /// Whether the material only scatters into this single direction, like
+    /// a mirror or glass. Such directions can't be evaluated, so lights are
+    /// never sampled for them.
+    pub delta: bool,
+}
+
+impl BsdfSample {
+    /// A sample of a delta distribution.
+    pub fn delta(ray: Ray, weight: Color) -> Self {
+        Self {
+            ray,
+            weight,
+            pdf: 0.0,
+            delta: true,
+        }
+    }
+}
+
+/// How a surface or a medium scatters light.
+///
+/// The older `scatter` returns a ray without its density. Materials only
+/// implementing `scatter` are sampled through it and treated as delta
+/// distributions, materials supporting importance sampling implement
+/// `eval`, `pdf` and `sample` as well.
 pub trait Material: Send + Sync {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)>;
 
-    fn emitted(&self, _u: f64, _v: f64, _p: &Point) -> Color {
+    /// The light sent back along `r_in` from its hit `rec`.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
         Color::zeros()
     }
+
+    /// Whether the material emits any light.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// The light scattered into `-r_in` per unit of light arriving from the
+    /// direction of `scattered`, the bsdf times the cosine to the normal.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Color {
+        Color::zeros()
+    }
+
+    /// The density, with respect to the solid angle, of `sample` picking the
+    /// direction of `scattered`. Zero for delta distributions.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Picks a scattered ray, by default the one of `scatter` as a delta
+    /// sample.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+        self.scatter(r_in, rec)
+            .map(|(attenuation, scattered)| BsdfSample::delta(scattered, attenuation))
+    }
+
+    /// A serializable description of the material.
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        description::not_describable(std::any::type_name::<Self>())
+    }
+}
+
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        (**self).scatter(r_in, rec)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        (**self).emitted(r_in, rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        (**self).is_emissive()
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        (**self).eval(r_in, rec, scattered)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        (**self).pdf(r_in, rec, scattered)
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+        (**self).sample(r_in, rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        (**self).describe(d)
+    }
 }
 
 #[derive(Clone)]
@@ -36,14 +132,36 @@ impl<T: Texture> Lambertian<T> {
 
 impl<T: Texture> Material for Lambertian<T> {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-        Some((
-            self.albedo.value(rec.u, rec.v, &rec.p),
-            Ray::with_time(rec.p, scatter_direction, r_in.time()),
-        ))
+        self.sample(r_in, rec).map(|s| (s.weight, s.ray))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.pdf(r_in, rec, scattered) * self.albedo.value_at(rec)
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = Vec3::dot(&rec.normal, &scattered.direction().unit_vector());
+        f64::max(cosine, 0.0) / PI
+    }
+
+    /// Picks directions with a cosine distribution, so the weight is the
+    /// albedo.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+        let direction = Onb::from_w(&rec.normal).local(&onb::random_cosine_direction());
+        let ray = Ray::with_time(rec.p, direction, r_in.time());
+
+        Some(BsdfSample {
+            pdf: self.pdf(r_in, rec, &ray),
+            ray,
+            weight: self.albedo.value_at(rec),
+            delta: false,
+        })
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        Ok(MaterialDescription::Lambertian {
+            albedo: d.texture(&self.albedo)?,
+        })
     }
 }
 
@@ -61,19 +179,66 @@ impl Metal {
 
 impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.sample(r_in, rec).map(|s| (s.weight, s.ray))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.pdf(r_in, rec, scattered) * self.albedo
+    }
+
+    /// The reflection is moved by a random point of a sphere with the radius
+    /// `fuzz`, so the density of a direction is the part of the sphere along
+    /// it. Directions below the surface are absorbed.
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let fuzz = self.fuzz.abs();
+        let direction = scattered.direction().unit_vector();
+        if fuzz == 0.0 || Vec3::dot(&direction, &rec.normal) <= 0.0 {
+            return 0.0;
+        }
+
+        // the distances along the direction to the surface of the sphere
         let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
-        let scattered = Ray::with_time(
-            rec.p,
-            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
-            r_in.time(),
-        );
-        let attenuation = self.albedo;
+        let b = Vec3::dot(&direction, &reflected);
+        let discriminant = b * b - reflected.length_squared() + fuzz * fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let far = b + discriminant.sqrt();
+        let near = f64::max(b - discriminant.sqrt(), 0.0);
+        if far <= near {
+            return 0.0;
+        }
 
-        if Vec3::dot(&scattered.direction(), &rec.normal) > 0.0 {
-            Some((attenuation, scattered))
-        } else {
-            None
+        // the volume of the cone inside the sphere over the volume of it
+        (far.powi(3) - near.powi(3)) / (4.0 * PI * fuzz.powi(3))
+    }
+
+    /// A sharp mirror, without fuzz, is a delta distribution.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+        let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
+        let direction = reflected + self.fuzz * Vec3::random_in_unit_sphere();
+        if Vec3::dot(&direction, &rec.normal) <= 0.0 {
+            return None;
+        }
+
+        let ray = Ray::with_time(rec.p, direction, r_in.time());
+        if self.fuzz == 0.0 {
+            return Some(BsdfSample::delta(ray, self.albedo));
         }
+
+        Some(BsdfSample {
+            pdf: self.pdf(r_in, rec, &ray),
+            ray,
+            weight: self.albedo,
+            delta: false,
+        })
+    }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        Ok(MaterialDescription::Metal {
+            albedo: self.albedo.into(),
+            fuzz: self.fuzz,
+        })
     }
 }
 
@@ -97,6 +262,11 @@ impl Dielectric {
 
 impl Material for Dielectric {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.sample(r_in, rec).map(|s| (s.weight, s.ray))
+    }
+
+    /// Glass either reflects or refracts into a single direction.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
         let attenuation = Color::new(1.0, 1.0, 1.0);
         let refraction_ratio = if rec.front_face {
             (1.0) / self.ir
@@ -120,13 +290,21 @@ impl Material for Dielectric {
 
         let scattered = Ray::with_time(rec.p, direction, r_in.time());
 
-        Some((attenuation, scattered))
+        Some(BsdfSample::delta(scattered, attenuation))
+    }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        Ok(MaterialDescription::Dielectric { ir: self.ir })
     }
 }
 
+/// Emits the light of its texture, by default equally from both sides and
+/// into all directions.
 #[derive(Clone)]
 pub struct DiffuseLight<T: Texture> {
     emit: T,
+    sides: Sides,
+    profile: Option<Profile>,
 }
 impl DiffuseLight<SolidColor> {
     pub fn new(c: Color) -> Self {
@@ -137,7 +315,32 @@ impl DiffuseLight<SolidColor> {
 
 impl<T: Texture> DiffuseLight<T> {
     pub fn with_texture(emit: T) -> Self {
-        Self { emit }
+        Self {
+            emit,
+            sides: Sides::Both,
+            profile: None,
+        }
+    }
+
+    /// Get the sides the light emits from.
+    pub fn sides(&self) -> Sides {
+        self.sides
+    }
+
+    /// Set the sides the light emits from.
+    pub fn set_sides(&mut self, sides: Sides) {
+        self.sides = sides;
+    }
+
+    /// Get the angular profile of the emission.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Set the angular profile of the emission, `None` emits equally into
+    /// all directions.
+    pub fn set_profile(&mut self, profile: Option<Profile>) {
+        self.profile = profile;
     }
 }
 
@@ -146,8 +349,38 @@ impl<T: Texture> Material for DiffuseLight<T> {
         None
     }
 
-    fn emitted(&self, u: f64, v: f64, p: &Point) -> Color {
-        self.emit.value(u, v, p)
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        let emits = match self.sides {
+            Sides::Both => true,
+            Sides::Front => rec.front_face,
+            Sides::Back => !rec.front_face,
+        };
+        if !emits {
+            return Color::zeros();
+        }
+
+        let factor = match &self.profile {
+            // the normal always faces the incoming ray
+            Some(profile) => {
+                let cos_theta = -Vec3::dot(&r_in.direction().unit_vector(), &rec.normal);
+                profile.value(cos_theta)
+            }
+            None => 1.0,
+        };
+
+        factor * self.emit.value_at(rec)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        Ok(MaterialDescription::DiffuseLight {
+            emit: d.texture(&self.emit)?,
+            sides: self.sides,
+            profile: self.profile.as_ref().map(|p| p.describe(d)).transpose()?,
+        })
     }
 }
 
@@ -172,8 +405,147 @@ where
 
 impl<T: Texture> Material for Isotropic<T> {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let scattered = Ray::with_time(rec.p, Vec3::random_in_unit_sphere(), r_in.time());
-        let attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
-        Some((attenuation, scattered))
+        self.sample(r_in, rec).map(|s| (s.weight, s.ray))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.pdf(r_in, rec, scattered) * self.albedo.value_at(rec)
+    }
+
+    /// Scatters uniformly into every direction.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+        let ray = Ray::with_time(rec.p, Vec3::random_unit_vector(), r_in.time());
+
+        Some(BsdfSample {
+            pdf: self.pdf(r_in, rec, &ray),
+            ray,
+            weight: self.albedo.value_at(rec),
+            delta: false,
+        })
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<MaterialDescription> {
+        Ok(MaterialDescription::Isotropic {
+            albedo: d.texture(&self.albedo)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, objects::rect, ray::Point};
+
+    #[test]
+    fn test_sample_matches_eval() {
+        let mut rec = HitRecord::default();
+        rec.p = Point::new(1.0, 2.0, 3.0);
+        rec.normal = Vec3::new(1.0, 1.0, 0.0).unit_vector();
+        rec.front_face = true;
+        let r_in = Ray::new(Point::new(3.0, 3.0, 3.0), rec.p - Point::new(3.0, 3.0, 3.0));
+
+        let materials: [Mat; 4] = [
+            Arc::new(Lambertian::new([0.2, 0.4, 0.8].into())),
+            Arc::new(Isotropic::new([0.5, 0.5, 0.5].into())),
+            Arc::new(Metal::new([0.8, 0.6, 0.2].into(), 0.3)),
+            Arc::new(Metal::new([0.8, 0.6, 0.2].into(), 1.0)),
+        ];
+        for mat in materials {
+            // fuzzy metals absorb the directions below the surface
+            for s in (0..100).filter_map(|_| mat.sample(&r_in, &rec)) {
+                assert!(!s.delta);
+                assert!((s.pdf - mat.pdf(&r_in, &rec, &s.ray)).abs() < 1e-9);
+                assert!(s.pdf > 0.0);
+
+                let expected = mat.eval(&r_in, &rec, &s.ray) / s.pdf;
+                assert!((s.weight - expected).near_zero());
+            }
+        }
+
+        let mirror = Metal::new([0.8, 0.8, 0.8].into(), 0.0);
+        let s = mirror.sample(&r_in, &rec).unwrap();
+        assert!(s.delta);
+        assert_eq!(mirror.pdf(&r_in, &rec, &s.ray), 0.0);
+
+        let glass = Dielectric::new(1.5);
+        let s = glass.sample(&r_in, &rec).unwrap();
+        assert!(s.delta);
+        assert_eq!(glass.pdf(&r_in, &rec, &s.ray), 0.0);
+    }
+
+    #[test]
+    fn test_metal_pdf() {
+        let mut rec = HitRecord::default();
+        rec.normal = Vec3::new(0.0, 1.0, 0.0);
+        rec.front_face = true;
+        let r_in = Ray::new(Point::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let reflected = Vec3::new(1.0, 1.0, 0.0).unit_vector();
+        const COS_CONE: f64 = 0.95;
+        let in_cone = |d: &Vec3| Vec3::dot(&d.unit_vector(), &reflected) > COS_CONE;
+
+        for fuzz in [0.2, 0.6, 1.0, 1.5] {
+            let metal = Metal::new(Color::ones(), fuzz);
+            let n = 100_000;
+
+            // the part of the samples in a cone around the reflection and
+            // the integral of the density over it, from uniform directions
+            // inside of the cone
+            let samples: Vec<_> = (0..n).filter_map(|_| metal.sample(&r_in, &rec)).collect();
+            let sampled = samples
+                .iter()
+                .filter(|s| in_cone(s.ray.direction()))
+                .count();
+            let sampled = sampled as f64 / n as f64;
+
+            let onb = Onb::from_w(&reflected);
+            let mut integral = 0.0;
+            for _ in 0..n {
+                let z = rtweekend::rand_range(COS_CONE..1.0);
+                let phi = rtweekend::rand_range(0.0..2.0 * PI);
+                let r = (1.0 - z * z).sqrt();
+                let d = onb.local(&Vec3::new(r * phi.cos(), r * phi.sin(), z));
+                integral += metal.pdf(&r_in, &rec, &Ray::new(rec.p, d));
+            }
+            let integral = integral * 2.0 * PI * (1.0 - COS_CONE) / n as f64;
+
+            assert!(
+                (sampled - integral).abs() < 0.02,
+                "fuzz {}: {} of the samples but an integral of {}",
+                fuzz,
+                sampled,
+                integral
+            );
+        }
+    }
+
+    #[test]
+    fn test_emitted() {
+        let mut light = DiffuseLight::new(Color::new(2.0, 2.0, 2.0));
+        light.set_sides(Sides::Back);
+        light.set_profile(Some(Profile::Spot {
+            cone_angle: 60.0,
+            falloff_angle: 30.0,
+        }));
+        let panel = rect::XZ::new(light, (-1.0, 1.0), (-1.0, 1.0), 0.0);
+
+        let emitted = |origin: Point| {
+            let r = Ray::new(origin, -origin);
+            let rec = panel.hit(&r, 0.001, f64::INFINITY).unwrap();
+            rec.mat.as_ref().unwrap().emitted(&r, &rec)
+        };
+
+        // only downwards, fading out towards the edge of the cone
+        assert_eq!(
+            emitted(Point::new(0.0, -1.0, 0.0)),
+            Color::new(2.0, 2.0, 2.0)
+        );
+        assert_eq!(emitted(Point::new(0.0, 1.0, 0.0)), Color::zeros());
+        let middle = emitted(Point::new(1.0, -1.0, 0.0)).x();
+        assert!(0.0 < middle && middle < 2.0);
+        assert_eq!(emitted(Point::new(1.0, -0.5, 0.0)), Color::zeros());
     }
 }
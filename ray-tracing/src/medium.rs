@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
+use anyhow::bail;
+
 use crate::{
     aabb::Aabb,
+    description::{Describer, MaterialDescription, ObjectDescription},
     hittable::{HitRecord, Hittable},
     material::{Isotropic, Material},
     rand_range,
@@ -93,4 +96,17 @@ where
 
         Some(rec)
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let albedo = match self.phase_function.describe(d)? {
+            MaterialDescription::Isotropic { albedo } => albedo,
+            other => bail!("unexpected phase function {:?}", other),
+        };
+
+        Ok(ObjectDescription::ConstantMedium {
+            density: -1.0 / self.neg_inv_density,
+            albedo,
+            boundary: Box::new(self.boundary.describe(d)?),
+        })
+    }
 }
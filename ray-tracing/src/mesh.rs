@@ -0,0 +1,477 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    description::{Describer, ObjectDescription},
+    helpers::{
+        obj::{self, FaceVertex, MtlMaterial},
+        ply, stl,
+    },
+    hittable::{HitRecord, Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, Mat, Metal},
+    objects,
+    ray::{Point, Ray, Vec3},
+    render::Color,
+    texture::ImageTexture,
+};
+
+/// The buffers and the material shared by all the triangles of a mesh.
+struct MeshData {
+    positions: Vec<Point>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<Color>>,
+    indices: Vec<[usize; 3]>,
+    mat: Mat,
+}
+
+/// A single face of a mesh, referencing the shared buffers.
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [&Point; 3] {
+        let [a, b, c] = self.mesh.indices[self.face];
+        let pos = &self.mesh.positions;
+        [&pos[a], &pos[b], &pos[c]]
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(objects::triangle_bounding_box(self.vertices()))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mesh = &self.mesh;
+        let [a, b, c] = mesh.indices[self.face];
+        let normals = mesh.normals.as_ref().map(|n| [&n[a], &n[b], &n[c]]);
+        let uvs = mesh.uvs.as_ref().map(|uv| [&uv[a], &uv[b], &uv[c]]);
+        let colors = mesh.colors.as_ref().map(|col| [&col[a], &col[b], &col[c]]);
+
+        objects::hit_triangle(
+            self.vertices(),
+            normals,
+            uvs,
+            colors,
+            &mesh.mat,
+            r,
+            t_min,
+            t_max,
+        )
+    }
+}
+
+/// An indexed triangle mesh.
+///
+/// All the triangles share the same vertex, normal, texture coordinate and
+/// color buffers and are stored inside of their own bvh.
+pub struct TriangleMesh {
+    tree: BvhNode,
+    mesh: Arc<MeshData>,
+}
+
+impl TriangleMesh {
+    /// Creates a mesh, failing for meshes without triangles or with indices
+    /// out of bounds of the positions.
+    pub fn new(positions: Vec<Point>, indices: Vec<[usize; 3]>, mat: Mat) -> anyhow::Result<Self> {
+        Self::with_attributes(positions, None, None, indices, mat)
+    }
+
+    /// Creates a mesh with optional per vertex normals and texture coordinates,
+    /// both are indexed the same way as the positions.
+    pub fn with_attributes(
+        positions: Vec<Point>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        indices: Vec<[usize; 3]>,
+        mat: Mat,
+    ) -> anyhow::Result<Self> {
+        Self::build(MeshData {
+            positions,
+            normals,
+            uvs,
+            colors: None,
+            indices,
+            mat,
+        })
+    }
+
+    /// Creates a mesh with a color per vertex, which a [`VertexColor`] texture
+    /// of `mat` interpolates across the faces.
+    ///
+    /// [`VertexColor`]: crate::texture::VertexColor
+    pub fn with_vertex_colors(
+        positions: Vec<Point>,
+        normals: Option<Vec<Vec3>>,
+        colors: Vec<Color>,
+        indices: Vec<[usize; 3]>,
+        mat: Mat,
+    ) -> anyhow::Result<Self> {
+        Self::build(MeshData {
+            positions,
+            normals,
+            uvs: None,
+            colors: Some(colors),
+            indices,
+            mat,
+        })
+    }
+
+    fn build(mesh: MeshData) -> anyhow::Result<Self> {
+        ensure!(
+            !mesh.indices.is_empty(),
+            "a mesh needs at least one triangle"
+        );
+
+        let len = mesh.positions.len();
+        if let Some(i) = mesh.indices.iter().flatten().find(|&&i| i >= len) {
+            bail!("triangle index {} out of bounds of the {} vertices", i, len);
+        }
+        if let Some(normals) = &mesh.normals {
+            ensure!(normals.len() == len, "every vertex needs a normal");
+        }
+        if let Some(uvs) = &mesh.uvs {
+            ensure!(uvs.len() == len, "every vertex needs a texture coordinate");
+        }
+        if let Some(colors) = &mesh.colors {
+            ensure!(colors.len() == len, "every vertex needs a color");
+        }
+
+        let mesh = Arc::new(mesh);
+
+        let mut list = HittableList::with_capacity(mesh.indices.len());
+        for face in 0..mesh.indices.len() {
+            list.add(MeshTriangle {
+                mesh: mesh.clone(),
+                face,
+            });
+        }
+
+        Ok(Self {
+            tree: BvhNode::from_hittable_list(&list, 0.0, 1.0),
+            mesh,
+        })
+    }
+
+    /// Get the number of triangles in the mesh.
+    pub fn len(&self) -> usize {
+        self.mesh.indices.len()
+    }
+
+    /// Returns `true` if the mesh contains no triangles.
+    pub fn is_empty(&self) -> bool {
+        self.mesh.indices.is_empty()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.tree.bounding_box(time0, time1)
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.tree.hit(r, t_min, t_max)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        let mesh = &self.mesh;
+        let vectors = |buf: &[Vec3]| buf.iter().map(|&v| v.into()).collect();
+
+        Ok(ObjectDescription::Mesh {
+            positions: vectors(&mesh.positions),
+            normals: mesh.normals.as_deref().map(vectors),
+            uvs: mesh
+                .uvs
+                .as_ref()
+                .map(|uv| uv.iter().map(|&(u, v)| [u, v]).collect()),
+            colors: mesh.colors.as_deref().map(vectors),
+            indices: mesh.indices.clone(),
+            material: d.material(&*mesh.mat)?,
+        })
+    }
+}
+
+/// The raw buffers of a mesh as read from a ply or stl file.
+#[derive(Debug, Default)]
+pub struct MeshBuffers {
+    pub positions: Vec<Point>,
+    pub normals: Option<Vec<Vec3>>,
+    pub colors: Option<Vec<Color>>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuffers {
+    /// Reads an ascii or binary (either endianness) ply file.
+    pub fn read_ply<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        ply::read(path)
+    }
+
+    /// Reads an ascii or binary stl file.
+    pub fn read_stl<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        stl::read(path)
+    }
+
+    /// Creates a mesh using a single material for all the faces,
+    /// vertex colors are ignored.
+    pub fn into_mesh(self, mat: Mat) -> anyhow::Result<TriangleMesh> {
+        TriangleMesh::with_attributes(self.positions, self.normals, None, self.indices, mat)
+    }
+
+    /// Creates a mesh colored by its vertex colors, see [`TriangleMesh::with_vertex_colors`].
+    pub fn into_colored_mesh(self, mat: Mat) -> anyhow::Result<TriangleMesh> {
+        let colors = match self.colors {
+            Some(colors) => colors,
+            None => bail!("the mesh has no vertex colors"),
+        };
+
+        TriangleMesh::with_vertex_colors(self.positions, self.normals, colors, self.indices, mat)
+    }
+}
+
+/// Loads a Wavefront obj file together with its mtl libraries.
+///
+/// Every run of faces sharing a group and a material becomes its own
+/// [`TriangleMesh`]. The mtl materials are mapped as follows:
+/// - `Ke` (if not black) to [`DiffuseLight`]
+/// - `d` below one or a transparent `illum` model to [`Dielectric`] using `Ni`
+/// - `Ks` (if brighter than `Kd` or `illum 3`) to [`Metal`], `Ns` controls the fuzz
+/// - otherwise `map_Kd` or `Kd` to [`Lambertian`]
+pub fn load_obj<P: AsRef<Path>>(path: P) -> anyhow::Result<HittableList> {
+    let data = obj::read(path)?;
+
+    let default: Mat = Arc::new(Lambertian::new([0.73, 0.73, 0.73].into()));
+    let mut materials: HashMap<&str, Mat> = HashMap::new();
+    let mut world = HittableList::with_capacity(data.groups.len());
+
+    for group in data.groups.iter() {
+        let mat = match group.material.as_deref() {
+            None => default.clone(),
+            Some(name) => match materials.get(name) {
+                Some(mat) => mat.clone(),
+                None => {
+                    let mtl = match data.materials.get(name) {
+                        Some(mtl) => mtl,
+                        None => bail!("unknown material {:?} in group {:?}", name, group.name),
+                    };
+                    let mat = convert_material(mtl)?;
+                    materials.insert(name, mat.clone());
+                    mat
+                }
+            },
+        };
+
+        // obj files index every attribute on their own, so every unique
+        // combination becomes a vertex of the mesh
+        let mut lookup: HashMap<FaceVertex, usize> = HashMap::new();
+        let mut corners = Vec::new();
+        let mut indices = Vec::with_capacity(group.faces.len());
+
+        for face in group.faces.iter() {
+            let mut tri = [0; 3];
+            for (idx, corner) in tri.iter_mut().zip(face) {
+                *idx = *lookup.entry(*corner).or_insert_with(|| {
+                    corners.push(*corner);
+                    corners.len() - 1
+                });
+            }
+            indices.push(tri);
+        }
+
+        let positions = corners.iter().map(|c| data.positions[c.position]).collect();
+        let normals = corners
+            .iter()
+            .map(|c| c.normal.map(|i| data.normals[i]))
+            .collect();
+        let uvs = corners
+            .iter()
+            .map(|c| c.texcoord.map(|i| data.texcoords[i]))
+            .collect();
+
+        let mesh = TriangleMesh::with_attributes(positions, normals, uvs, indices, mat)
+            .with_context(|| format!("invalid faces in group {:?}", group.name))?;
+        world.add(mesh);
+    }
+
+    Ok(world)
+}
+
+fn convert_material(mtl: &MtlMaterial) -> anyhow::Result<Mat> {
+    let brightest = |c: &Color| c.data().iter().cloned().fold(0.0, f64::max);
+
+    let transparent = matches!(mtl.illum, Some(4 | 6 | 7 | 9));
+
+    let mat: Mat = if brightest(&mtl.emission) > 0.0 {
+        Arc::new(DiffuseLight::new(mtl.emission))
+    } else if mtl.dissolve < 1.0 || transparent {
+        Arc::new(Dielectric::new(mtl.ior))
+    } else if brightest(&mtl.specular) > 0.0
+        && (mtl.illum == Some(3) || brightest(&mtl.specular) >= brightest(&mtl.diffuse))
+    {
+        // Map the phong exponent to a roughness like value
+        let fuzz = f64::sqrt(2.0 / (mtl.shininess + 2.0));
+        Arc::new(Metal::new(mtl.specular, fuzz.min(1.0)))
+    } else if let Some(path) = &mtl.diffuse_map {
+        let tex = ImageTexture::new(path)
+            .with_context(|| format!("unable to load texture {}", path.display()))?;
+        Arc::new(Lambertian::with_texture(tex))
+    } else {
+        Arc::new(Lambertian::new(mtl.diffuse))
+    };
+
+    Ok(mat)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{material::Lambertian, objects::Triangle, texture::VertexColor};
+
+    fn mat() -> Mat {
+        Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()))
+    }
+
+    fn quad() -> TriangleMesh {
+        let positions = vec![
+            [0.0, 0.0, 0.0].into(),
+            [1.0, 0.0, 0.0].into(),
+            [1.0, 1.0, 0.0].into(),
+            [0.0, 1.0, 0.0].into(),
+        ];
+        TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], mat()).unwrap()
+    }
+
+    #[test]
+    fn test_triangle_hit() {
+        let tri = Triangle::new(
+            [
+                [0.0, 0.0, 0.0].into(),
+                [1.0, 0.0, 0.0].into(),
+                [0.0, 1.0, 0.0].into(),
+            ],
+            mat(),
+        );
+
+        let r = Ray::new([0.25, 0.25, 1.0].into(), [0.0, 0.0, -1.0].into());
+        let rec = tri
+            .hit(&r, 0.001, f64::INFINITY)
+            .expect("the ray should hit");
+
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12);
+        assert!((rec.v - 0.25).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, [0.0, 0.0, 1.0].into());
+
+        let r = Ray::new([0.75, 0.75, 1.0].into(), [0.0, 0.0, -1.0].into());
+        assert!(tri.hit(&r, 0.001, f64::INFINITY).is_none());
+
+        let r = Ray::new([0.25, 0.25, 1.0].into(), [1.0, 0.0, 0.0].into());
+        assert!(tri.hit(&r, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_mesh_hit() {
+        let mesh = quad();
+        assert_eq!(mesh.len(), 2);
+
+        for (x, y) in [(0.9, 0.1), (0.1, 0.9), (0.5, 0.5)] {
+            let r = Ray::new([x, y, -1.0].into(), [0.0, 0.0, 1.0].into());
+            let rec = mesh
+                .hit(&r, 0.001, f64::INFINITY)
+                .expect("the ray should hit");
+            assert!((rec.t - 1.0).abs() < 1e-12);
+            assert!(!rec.front_face);
+        }
+
+        let r = Ray::new([1.5, 0.5, -1.0].into(), [0.0, 0.0, 1.0].into());
+        assert!(mesh.hit(&r, 0.001, f64::INFINITY).is_none());
+
+        let positions = || vec![Point::zeros(); 3];
+        assert!(TriangleMesh::new(positions(), Vec::new(), mat()).is_err());
+        assert!(TriangleMesh::new(positions(), vec![[0, 1, 3]], mat()).is_err());
+        let normals = Some(vec![Vec3::zeros(); 2]);
+        let mesh =
+            TriangleMesh::with_attributes(positions(), normals, None, vec![[0, 1, 2]], mat());
+        assert!(mesh.is_err());
+    }
+
+    #[test]
+    fn test_load_obj() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        std::fs::write(
+            dir.path().join("scene.mtl"),
+            "newmtl light\nKe 1 1 1\nnewmtl white\nKd 0.5 0.5 0.5\n",
+        )?;
+        std::fs::write(
+            dir.path().join("scene.obj"),
+            "mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+o lamp
+usemtl light
+f 1 2 3
+o floor
+usemtl white
+f 1 2 3 4
+",
+        )?;
+
+        let world = load_obj(dir.path().join("scene.obj"))?;
+        assert_eq!(world.objects().len(), 2);
+
+        let r = Ray::new([0.9, 0.1, -1.0].into(), [0.0, 0.0, 1.0].into());
+        assert!(world.hit(&r, 0.001, f64::INFINITY).is_some());
+
+        std::fs::write(
+            dir.path().join("broken.obj"),
+            "v 0 0 0\nusemtl missing\nf 1 1 1\n",
+        )?;
+        assert!(load_obj(dir.path().join("broken.obj")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colored_ply() -> anyhow::Result<()> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/quad_ascii.ply");
+        let mesh = MeshBuffers::read_ply(path)?
+            .into_colored_mesh(Arc::new(Lambertian::with_texture(VertexColor)))?;
+
+        // right next to the first, red, vertex
+        let r = Ray::new([0.001, 0.001, -1.0].into(), [0.0, 0.0, 1.0].into());
+        let rec = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+        let (color, _) = rec.mat.as_ref().unwrap().scatter(&r, &rec).unwrap();
+
+        assert!(color.x() > 0.99 && color.y() < 0.01 && color.z() < 0.01);
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/tetrahedron_ascii.stl"
+        );
+        assert!(MeshBuffers::read_stl(path)?
+            .into_colored_mesh(Arc::new(Lambertian::with_texture(VertexColor)))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mesh_bounding_box() {
+        let bbox = quad().bounding_box(0.0, 1.0).unwrap();
+
+        assert!(bbox.min().x() <= 0.0 && bbox.max().x() >= 1.0);
+        assert!(bbox.min().y() <= 0.0 && bbox.max().y() >= 1.0);
+        assert!(bbox.min().z() < 0.0 && bbox.max().z() > 0.0);
+    }
+}
@@ -2,11 +2,24 @@ use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
     aabb::Aabb,
+    description::{Describer, ObjectDescription},
     hittable::{HitRecord, Hittable, HittableList},
     material::{Mat, Material},
+    onb::Onb,
+    rand_range,
     ray::{Point, Ray, Vec3},
+    render::Color,
 };
 
+/// The density, with respect to the solid angle seen from the origin of `r`,
+/// of picking the point hit by `r` uniformly on a flat shape of the given area.
+fn area_pdf(r: &Ray, rec: &HitRecord, area: f64) -> f64 {
+    let distance_squared = rec.t * rec.t * r.direction().length_squared();
+    let cosine = (Vec3::dot(r.direction(), &rec.normal) / r.direction().length()).abs();
+
+    distance_squared / (cosine * area)
+}
+
 #[derive(Clone)]
 pub struct Sphere {
     pub center: Point,
@@ -43,7 +56,7 @@ impl Hittable for Sphere {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let oc = *r.origin() - self.center;
         let a = r.direction().length_squared();
-        let half_b = Vec3::dot(&oc, &r.direction());
+        let half_b = Vec3::dot(&oc, r.direction());
         let c = oc.length_squared() - self.radius * self.radius;
 
         let discriminant = half_b * half_b - a * c;
@@ -82,6 +95,57 @@ impl Hittable for Sphere {
         let v = [self.radius; 3].into();
         Some(Aabb::new(self.center - v, self.center + v))
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Sphere {
+            center: self.center.into(),
+            radius: self.radius,
+            material: d.material(&*self.mat)?,
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.mat.is_emissive()
+    }
+
+    /// Directions are sampled uniformly in the cone around the sphere, or
+    /// in every direction from inside of it.
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let r = Ray::new(*origin, *direction);
+        if self.hit(&r, 0.001, f64::INFINITY).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - *origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * PI);
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::random_unit_vector();
+        }
+
+        let r1: f64 = rand_range(0.0..1.0);
+        let r2: f64 = rand_range(0.0..1.0);
+        let z = 1.0 + r2 * ((1.0 - radius_squared / distance_squared).sqrt() - 1.0);
+
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+
+        Onb::from_w(&direction).local(&local)
+    }
 }
 
 pub struct MovingSphere {
@@ -111,7 +175,7 @@ impl Hittable for MovingSphere {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let oc = *r.origin() - self.center(r.time());
         let a = r.direction().length_squared();
-        let half_b = Vec3::dot(&oc, &r.direction());
+        let half_b = Vec3::dot(&oc, r.direction());
         let c = oc.length_squared() - self.radius * self.radius;
 
         let discriminant = half_b * half_b - a * c;
@@ -153,12 +217,24 @@ impl Hittable for MovingSphere {
 
         Some(Aabb::surrounding_box(&box0, &box1))
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::MovingSphere {
+            center0: self.center.0.into(),
+            center1: self.center.1.into(),
+            time0: self.time.0,
+            time1: self.time.1,
+            radius: self.radius,
+            material: d.material(&*self.mat)?,
+        })
+    }
 }
 
 pub struct Cube {
     box_min: Point,
     box_max: Point,
     sides: HittableList,
+    mat: Mat,
 }
 
 impl Cube {
@@ -191,9 +267,10 @@ impl Cube {
         }
 
         Self {
-            box_min: p0.clone(),
-            box_max: p1.clone(),
+            box_min: *p0,
+            box_max: *p1,
             sides,
+            mat,
         }
     }
 }
@@ -206,6 +283,209 @@ impl Hittable for Cube {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         self.sides.hit(r, t_min, t_max)
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Cube {
+            min: self.box_min.into(),
+            max: self.box_max.into(),
+            material: d.material(&*self.mat)?,
+        })
+    }
+}
+
+/// Tolerance used to reject rays that are (nearly) parallel to a triangle.
+const TRIANGLE_EPSILON: f64 = 1e-9;
+
+/// Ray / triangle intersection using the Möller–Trumbore algorithm.
+///
+/// Returns (t, b1, b2) where b1 and b2 are the barycentric coordinates
+/// of the hit point relative to the second and third vertex.
+pub(crate) fn intersect_triangle(
+    vertices: [&Point; 3],
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let [v0, v1, v2] = vertices;
+    let edge1 = *v1 - *v0;
+    let edge2 = *v2 - *v0;
+
+    let pvec = Vec3::cross(r.direction(), &edge2);
+    let det = Vec3::dot(&edge1, &pvec);
+
+    if det.abs() < TRIANGLE_EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = *r.origin() - *v0;
+
+    let b1 = Vec3::dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(&tvec, &edge1);
+    let b2 = Vec3::dot(r.direction(), &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = Vec3::dot(&edge2, &qvec) * inv_det;
+    if t < t_min || t_max < t {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+/// Intersects the triangle and fills in the hit record.
+///
+/// Normals, texture coordinates and colors are interpolated if given,
+/// otherwise the geometric normal and the barycentric coordinates are used.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn hit_triangle(
+    vertices: [&Point; 3],
+    normals: Option<[&Vec3; 3]>,
+    uvs: Option<[&(f64, f64); 3]>,
+    colors: Option<[&Color; 3]>,
+    mat: &Mat,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord> {
+    let (t, b1, b2) = intersect_triangle(vertices, r, t_min, t_max)?;
+    let b0 = 1.0 - b1 - b2;
+    let [v0, v1, v2] = vertices;
+
+    let mut rec = HitRecord::default();
+
+    rec.t = t;
+    rec.p = r.at(t);
+
+    let outward_normal = match normals {
+        Some([n0, n1, n2]) => (b0 * *n0 + b1 * *n1 + b2 * *n2).unit_vector(),
+        None => Vec3::cross(&(*v1 - *v0), &(*v2 - *v0)).unit_vector(),
+    };
+    rec.set_face_normal(r, &outward_normal);
+
+    let (u, v) = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+            b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+        ),
+        None => (b1, b2),
+    };
+    rec.u = u;
+    rec.v = v;
+    rec.color = colors.map(|[c0, c1, c2]| b0 * *c0 + b1 * *c1 + b2 * *c2);
+    rec.mat = Some(mat.clone());
+
+    Some(rec)
+}
+
+/// The bounding box of a triangle.
+pub(crate) fn triangle_bounding_box(vertices: [&Point; 3]) -> Aabb {
+    // The bounding box must have non-zero width in each dimension, so pad
+    // every dimension a small amount for axis aligned triangles.
+    const PAD: f64 = 0.0001;
+
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+
+    for v in vertices {
+        for (i, &val) in v.data().iter().enumerate() {
+            min[i] = f64::min(min[i], val - PAD);
+            max[i] = f64::max(max[i], val + PAD);
+        }
+    }
+
+    Aabb::new(min.into(), max.into())
+}
+
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Point; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat: Mat,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point; 3], mat: Mat) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            mat,
+        }
+    }
+
+    /// Set the per vertex normals, which are interpolated across the face.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Set the per vertex texture coordinates, which are interpolated across the face.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Get a reference to the triangle's vertices.
+    pub fn vertices(&self) -> &[Point; 3] {
+        &self.vertices
+    }
+}
+
+impl Hittable for Triangle {
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        let [v0, v1, v2] = &self.vertices;
+        Some(triangle_bounding_box([v0, v1, v2]))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let [v0, v1, v2] = &self.vertices;
+        let normals = self.normals.as_ref().map(|[n0, n1, n2]| [n0, n1, n2]);
+        let uvs = self.uvs.as_ref().map(|[uv0, uv1, uv2]| [uv0, uv1, uv2]);
+
+        hit_triangle([v0, v1, v2], normals, uvs, None, &self.mat, r, t_min, t_max)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Triangle {
+            vertices: self.vertices.map(Into::into),
+            normals: self.normals.map(|n| n.map(Into::into)),
+            uvs: self.uvs.map(|uvs| uvs.map(|(u, v)| [u, v])),
+            material: d.material(&*self.mat)?,
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.mat.is_emissive()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        let r = Ray::new(*origin, *direction);
+        match self.hit(&r, 0.001, f64::INFINITY) {
+            Some(rec) => {
+                let [v0, v1, v2] = self.vertices;
+                let area = 0.5 * Vec3::cross(&(v1 - v0), &(v2 - v0)).length();
+                area_pdf(&r, &rec, area)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Picks a point uniformly on the triangle.
+    fn random(&self, origin: &Point) -> Vec3 {
+        let [v0, v1, v2] = self.vertices;
+        let su = rand_range(0.0..1.0f64).sqrt();
+        let (b0, b1) = (1.0 - su, rand_range(0.0..1.0) * su);
+
+        b0 * v0 + b1 * v1 + (1.0 - b0 - b1) * v2 - *origin
+    }
 }
 
 pub mod rect {
@@ -273,6 +553,40 @@ pub mod rect {
 
             Some(rec)
         }
+
+        fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+            Ok(ObjectDescription::XyRect {
+                x: [self.x.0, self.x.1],
+                y: [self.y.0, self.y.1],
+                k: self.k,
+                material: d.material(&*self.mp)?,
+            })
+        }
+
+        fn is_light(&self) -> bool {
+            self.mp.is_emissive()
+        }
+
+        fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+            let r = Ray::new(*origin, *direction);
+            match self.hit(&r, 0.001, f64::INFINITY) {
+                Some(rec) => {
+                    let area = (self.x.1 - self.x.0) * (self.y.1 - self.y.0);
+                    area_pdf(&r, &rec, area)
+                }
+                None => 0.0,
+            }
+        }
+
+        fn random(&self, origin: &Point) -> Vec3 {
+            let p: Point = [
+                rand_range(self.x.0..self.x.1),
+                rand_range(self.y.0..self.y.1),
+                self.k,
+            ]
+            .into();
+            p - *origin
+        }
     }
 
     pub struct XZ<M: Material> {
@@ -335,6 +649,40 @@ pub mod rect {
 
             Some(rec)
         }
+
+        fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+            Ok(ObjectDescription::XzRect {
+                x: [self.x.0, self.x.1],
+                z: [self.z.0, self.z.1],
+                k: self.k,
+                material: d.material(&*self.mp)?,
+            })
+        }
+
+        fn is_light(&self) -> bool {
+            self.mp.is_emissive()
+        }
+
+        fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+            let r = Ray::new(*origin, *direction);
+            match self.hit(&r, 0.001, f64::INFINITY) {
+                Some(rec) => {
+                    let area = (self.x.1 - self.x.0) * (self.z.1 - self.z.0);
+                    area_pdf(&r, &rec, area)
+                }
+                None => 0.0,
+            }
+        }
+
+        fn random(&self, origin: &Point) -> Vec3 {
+            let p: Point = [
+                rand_range(self.x.0..self.x.1),
+                self.k,
+                rand_range(self.z.0..self.z.1),
+            ]
+            .into();
+            p - *origin
+        }
     }
 
     pub struct YZ<M: Material + 'static> {
@@ -396,5 +744,39 @@ pub mod rect {
 
             Some(rec)
         }
+
+        fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+            Ok(ObjectDescription::YzRect {
+                y: [self.y.0, self.y.1],
+                z: [self.z.0, self.z.1],
+                k: self.k,
+                material: d.material(&*self.mp)?,
+            })
+        }
+
+        fn is_light(&self) -> bool {
+            self.mp.is_emissive()
+        }
+
+        fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+            let r = Ray::new(*origin, *direction);
+            match self.hit(&r, 0.001, f64::INFINITY) {
+                Some(rec) => {
+                    let area = (self.y.1 - self.y.0) * (self.z.1 - self.z.0);
+                    area_pdf(&r, &rec, area)
+                }
+                None => 0.0,
+            }
+        }
+
+        fn random(&self, origin: &Point) -> Vec3 {
+            let p: Point = [
+                self.k,
+                rand_range(self.y.0..self.y.1),
+                rand_range(self.z.0..self.z.1),
+            ]
+            .into();
+            p - *origin
+        }
     }
 }
@@ -0,0 +1,95 @@
+use std::f64::consts::PI;
+
+use crate::{rand_range, ray::Vec3};
+
+/// An orthonormal basis, used to turn directions sampled around the z axis
+/// into directions around any other axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    /// A basis with the direction of `w` as its z axis, `w` doesn't have to
+    /// be normalized.
+    pub fn from_w(w: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross(&w, &a).unit_vector();
+        let u = Vec3::cross(&w, &v);
+
+        Self { u, v, w }
+    }
+
+    pub fn u(&self) -> &Vec3 {
+        &self.u
+    }
+
+    pub fn v(&self) -> &Vec3 {
+        &self.v
+    }
+
+    pub fn w(&self) -> &Vec3 {
+        &self.w
+    }
+
+    /// Turns the coordinates `a` in this basis into world space.
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+/// A direction in the upper hemisphere around the z axis with a density of
+/// `cos(theta) / PI`.
+pub fn random_cosine_direction() -> Vec3 {
+    let r1: f64 = rand_range(0.0..1.0);
+    let r2: f64 = rand_range(0.0..1.0);
+
+    let phi = 2.0 * PI * r1;
+    let z = (1.0 - r2).sqrt();
+    let r = r2.sqrt();
+
+    Vec3::new(phi.cos() * r, phi.sin() * r, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basis() {
+        for _ in 0..100 {
+            let w = Vec3::random_range(-5.0..5.0);
+            let onb = Onb::from_w(&w);
+
+            for (a, b) in [(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+                assert!(Vec3::dot(&a, &b).abs() < 1e-9);
+            }
+            for a in [onb.u, onb.v, onb.w] {
+                assert!((a.length() - 1.0).abs() < 1e-9);
+            }
+            assert!((onb.local(&Vec3::new(0.0, 0.0, 1.0)) - w.unit_vector()).near_zero());
+        }
+    }
+
+    #[test]
+    fn test_cosine_direction() {
+        // the mean of cos(theta) is 2 / 3 for a cosine distribution
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let d = random_cosine_direction();
+            assert!((d.length() - 1.0).abs() < 1e-9);
+            assert!(d.z() >= 0.0);
+            sum += d.z();
+        }
+
+        assert!((sum / n as f64 - 2.0 / 3.0).abs() < 0.01);
+    }
+}
@@ -24,6 +24,7 @@ impl Perlin {
         }
     }
 
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: &Point) -> f64 {
         let calc = |v: f64| v - v.floor();
 
@@ -57,7 +58,7 @@ impl Perlin {
 
     pub fn turb_with_depth(&self, p: &Point, depth: usize) -> f64 {
         let mut accum = 0.0;
-        let mut temp_p = p.clone();
+        let mut temp_p = *p;
         let mut weight = 1.0;
 
         for _ in 0..depth {
@@ -69,6 +70,7 @@ impl Perlin {
         accum.abs()
     }
 
+    #[allow(clippy::needless_range_loop)]
     fn perlin_interp(c: &[[[Vec3; 2]; 2]], u: f64, v: f64, w: f64) -> f64 {
         let calc = |v: f64| v * v * (3.0 - 2.0 * v);
 
@@ -99,7 +101,7 @@ impl Perlin {
     }
 
     // from part 5.4
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::needless_range_loop)]
     fn trilinear_interp(c: &[[[f64; 2]; 2]], u: f64, v: f64, w: f64) -> f64 {
         let mut accum = 0.0;
         for ii in 0..2 {
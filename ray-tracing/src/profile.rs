@@ -0,0 +1,272 @@
+//! Angular profiles shaping the emission of a [`DiffuseLight`].
+//!
+//! [`DiffuseLight`]: crate::material::DiffuseLight
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clamp,
+    description::{Describer, ProfileDescription},
+    light::cone_falloff,
+};
+
+/// The sides of a surface a light emits from. The front is the side the
+/// outward normal of the object points to, for the rects the positive axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sides {
+    #[default]
+    Both,
+    Front,
+    Back,
+}
+
+/// Scales the emitted light by the angle to the normal of the emitting side.
+#[derive(Debug, Clone)]
+pub enum Profile {
+    /// Full within `falloff_angle` of the normal and fading out smoothly
+    /// towards `cone_angle`, both in degrees.
+    Spot { cone_angle: f64, falloff_angle: f64 },
+    /// A measured table, like the ones of IES files.
+    Goniometric(Goniometric),
+}
+
+impl Profile {
+    /// The factor of the light leaving at an angle with the cosine
+    /// `cos_theta` to the normal.
+    pub fn value(&self, cos_theta: f64) -> f64 {
+        match self {
+            Self::Spot {
+                cone_angle,
+                falloff_angle,
+            } => cone_falloff(cos_theta, *cone_angle, *falloff_angle),
+            Self::Goniometric(table) => {
+                table.value(clamp(cos_theta, -1.0, 1.0).acos().to_degrees())
+            }
+        }
+    }
+
+    /// A serializable description of the profile.
+    pub fn describe(&self, d: &mut Describer) -> anyhow::Result<ProfileDescription> {
+        match self {
+            Self::Spot {
+                cone_angle,
+                falloff_angle,
+            } => Ok(ProfileDescription::Spot {
+                cone_angle: *cone_angle,
+                falloff_angle: *falloff_angle,
+            }),
+            Self::Goniometric(table) => match table.path() {
+                Some(path) => Ok(ProfileDescription::Ies { path: d.path(path) }),
+                None => bail!("a goniometric profile not loaded from a file can not be described"),
+            },
+        }
+    }
+}
+
+/// The most angles an IES file may give in either direction, real files use
+/// a few hundred at most.
+const MAX_ANGLES: usize = 100_000;
+
+/// The relative intensity at a set of angles to the normal, linearly
+/// interpolated in between and zero beyond the last angle.
+#[derive(Debug, Clone)]
+pub struct Goniometric {
+    path: Option<PathBuf>,
+    /// In degrees, increasing.
+    angles: Vec<f64>,
+    /// Scaled to a maximum of one, the light keeps the brightness of its
+    /// texture along the brightest direction.
+    values: Vec<f64>,
+}
+
+impl Goniometric {
+    /// The `angles` are in degrees and have to increase.
+    pub fn new(angles: Vec<f64>, values: Vec<f64>) -> anyhow::Result<Self> {
+        ensure!(
+            !angles.is_empty(),
+            "a goniometric table needs at least one angle"
+        );
+        ensure!(
+            angles.len() == values.len(),
+            "{} angles but {} values",
+            angles.len(),
+            values.len()
+        );
+        ensure!(
+            angles.windows(2).all(|w| w[0] < w[1]),
+            "the angles have to increase"
+        );
+
+        let max = values.iter().cloned().fold(0.0, f64::max);
+        ensure!(max > 0.0, "a goniometric table needs a positive value");
+
+        Ok(Self {
+            path: None,
+            angles,
+            values: values.iter().map(|v| v.max(0.0) / max).collect(),
+        })
+    }
+
+    /// Loads the vertical angles of an IES LM-63 file, averaged over its
+    /// horizontal angles. The nadir, straight down from the luminaire, is
+    /// taken as the normal of the light.
+    ///
+    /// Only type C photometry is supported, the common one for luminaires,
+    /// types A and B measure their angles around a horizontal axis.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path)
+            .with_context(|| format!("unable to read IES file {}", path.display()))?;
+
+        let mut table = Self::parse_ies(&src)
+            .with_context(|| format!("unable to parse IES file {}", path.display()))?;
+        table.path = Some(path.to_path_buf());
+
+        Ok(table)
+    }
+
+    fn parse_ies(src: &str) -> anyhow::Result<Self> {
+        let mut lines = src.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find(|l| l.starts_with("TILT="))
+            .context("missing the TILT line")?;
+        ensure!(tilt == "TILT=NONE", "only TILT=NONE is supported");
+
+        let numbers = lines
+            .flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                t.parse::<f64>()
+                    .with_context(|| format!("invalid number {}", t))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // the lamps, the lumens per lamp, the multiplier, the angle counts,
+        // the photometric type, the units, the size, the ballast factor, a
+        // reserved field and the input watts
+        ensure!(numbers.len() >= 13, "the header is incomplete");
+        let count = |n: f64| {
+            ensure!(
+                n.fract() == 0.0 && (1.0..=MAX_ANGLES as f64).contains(&n),
+                "invalid angle count {}",
+                n
+            );
+            Ok(n as usize)
+        };
+        let (vertical, horizontal) = (count(numbers[3])?, count(numbers[4])?);
+        ensure!(
+            numbers[5] == 1.0,
+            "only type C photometry is supported, not type {}",
+            numbers[5]
+        );
+
+        let data = &numbers[13..];
+        let len = vertical
+            .checked_mul(horizontal)
+            .and_then(|n| n.checked_add(vertical + horizontal))
+            .context("the table is too large")?;
+        ensure!(data.len() >= len, "the table is incomplete");
+
+        let angles = data[..vertical].to_vec();
+        let candelas = &data[vertical + horizontal..][..vertical * horizontal];
+        let values = (0..vertical)
+            .map(|i| {
+                let sum: f64 = candelas.chunks(vertical).map(|c| c[i]).sum();
+                sum / horizontal as f64
+            })
+            .collect();
+
+        Self::new(angles, values)
+    }
+
+    /// Get the path the table was loaded from.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The relative intensity at `angle` degrees to the normal.
+    pub fn value(&self, angle: f64) -> f64 {
+        let i = self.angles.partition_point(|&a| a <= angle);
+        if i == 0 {
+            return self.values[0];
+        }
+        if i == self.angles.len() {
+            // the last angle itself is still in the table
+            return if angle <= self.angles[i - 1] {
+                self.values[i - 1]
+            } else {
+                0.0
+            };
+        }
+
+        let (a0, a1) = (self.angles[i - 1], self.angles[i]);
+        let t = (angle - a0) / (a1 - a0);
+        (1.0 - t) * self.values[i - 1] + t * self.values[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IES: &str = "IESNA:LM-63-2002
+[TEST] a narrow downlight
+TILT=NONE
+1 1000 1 4 2 1 2 0 0 0
+1 1 100
+0 30 60 90
+0 180
+800 600 100 0
+1000 600 100 0
+";
+
+    #[test]
+    fn test_goniometric() -> anyhow::Result<()> {
+        let table = Goniometric::new(vec![10.0, 40.0], vec![4.0, 2.0])?;
+        assert_eq!(table.value(0.0), 1.0);
+        assert_eq!(table.value(25.0), 0.75);
+        assert_eq!(table.value(40.0), 0.5);
+        assert_eq!(table.value(41.0), 0.0);
+
+        assert!(Goniometric::new(vec![10.0, 5.0], vec![1.0, 1.0]).is_err());
+        assert!(Goniometric::new(vec![10.0], vec![0.0]).is_err());
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("downlight.ies");
+        fs::write(&path, IES)?;
+
+        // the horizontal angles are averaged
+        let table = Goniometric::load(&path)?;
+        assert_eq!(table.path(), Some(path.as_path()));
+        assert_eq!(table.value(0.0), 1.0);
+        assert_eq!(table.value(30.0), 600.0 / 900.0);
+        assert_eq!(table.value(90.0), 0.0);
+
+        let profile = Profile::Goniometric(table);
+        assert!((profile.value(0.5) - 100.0 / 900.0).abs() < 1e-9);
+
+        for broken in [
+            IES.replace("TILT=NONE", "TILT=INCLUDE"),
+            // fractional, negative and huge angle counts
+            IES.replace("1 1000 1 4 2", "1 1000 1 4.5 2"),
+            IES.replace("1 1000 1 4 2", "1 1000 1 -4 2"),
+            IES.replace("1 1000 1 4 2", "1 1000 1 1e300 1e300"),
+            // type B photometry
+            IES.replace("4 2 1 2", "4 2 2 2"),
+        ] {
+            fs::write(&path, &broken)?;
+            assert!(Goniometric::load(&path).is_err(), "{}", broken);
+        }
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use crate::helpers;
 pub type Point = helpers::cvec::Point<f64>;
 pub type Vec3 = helpers::cvec::Vec3<f64>;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     orig: Point,
     dir: Vec3,
@@ -49,7 +49,7 @@ pub trait Render<'a> {
     fn image(&self) -> Image<'_>;
 }
 
-#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileFormat {
     PPM,
     PNG,
@@ -30,7 +30,7 @@ pub fn save<'a, T: Render<'a>, P: AsRef<Path>>(
         let b = p.z() as u8;
 
         // no error possible as per docs
-        let _ = write!(s, "{} {} {}\n", r, g, b);
+        let _ = writeln!(s, "{} {} {}", r, g, b);
     }
 
     let path = path.as_ref().to_string_lossy();
@@ -63,7 +63,7 @@ mod tests {
 
         let mut res = format!("P3\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT);
 
-        let per = |v, c| ((v as f64) / ((c - 1) as f64));
+        let per = |v, c| (v as f64) / ((c - 1) as f64);
 
         let rcon = |v| 255.999 * v;
         let con = |v| rcon(v) as u8;
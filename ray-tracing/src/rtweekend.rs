@@ -1,12 +1,18 @@
 use rand::{
     distributions::uniform::{SampleRange, SampleUniform},
-    Rng,
+    rngs::SmallRng,
+    Rng, SeedableRng,
 };
-use std::f64::consts::PI;
+use std::{cell::RefCell, f64::consts::PI};
+
+thread_local! {
+    /// Every thread starts with the same sequence, which keeps the scene
+    /// creation reproducible. The renderer reseeds it for every sample.
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(0));
+}
 
 #[inline]
 pub fn degrees_to_radians(deg: f64) -> f64 {
-    debug_assert!(deg >= 0.0 && deg <= 360.0);
     deg * PI / 180.0
 }
 
@@ -27,5 +33,27 @@ where
     T: SampleUniform,
     R: SampleRange<T>,
 {
-    rand::thread_rng().gen_range(range)
+    RNG.with(|rng| rng.borrow_mut().gen_range(range))
+}
+
+/// Restarts the random number generator of the current thread.
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+/// Derives the seed of an independent random stream from a base seed and
+/// a pair of counters, e.g. the index of a pixel and of a sample.
+pub fn stream_seed(seed: u64, a: u64, b: u64) -> u64 {
+    // The finalizer of splitmix64, which scrambles consecutive inputs well.
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    const GOLDEN_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+    let h = mix(seed.wrapping_add(GOLDEN_GAMMA));
+    let h = mix(h ^ a.wrapping_add(GOLDEN_GAMMA));
+    mix(h ^ b.wrapping_add(GOLDEN_GAMMA))
 }
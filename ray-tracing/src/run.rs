@@ -1,7 +1,18 @@
+use std::sync::Arc;
+
 use cfg_if::cfg_if;
 use rayon::prelude::*;
 
-use crate::{camera::Camera, clamp, hittable::Hittable, ray::Ray, render::Color};
+use crate::{
+    background::Background,
+    camera::Camera,
+    clamp,
+    hittable::{Hittable, HittableList},
+    integrator::{Integrator, Scene},
+    light::Light,
+    render::Color,
+    seed_rng, stream_seed,
+};
 
 const ASPECT_RATIO: f64 = 16.0 / 9.0;
 const IMAGE_WIDTH: usize = 160 * 4;
@@ -9,6 +20,7 @@ const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
 const SAMPLES_PER_PIXEL: usize = 100;
 const MAX_DEPTH: usize = 50;
 const GAMMA: f64 = 2.0;
+const SEED: u64 = 0;
 
 #[derive(Clone)]
 pub struct Config {
@@ -19,6 +31,9 @@ pub struct Config {
     max_depth: usize,
     gamma: f64,
     background: Color,
+    environment: Option<Arc<dyn Background>>,
+    lights: Vec<Arc<dyn Light>>,
+    seed: u64,
 }
 
 impl Config {
@@ -82,6 +97,46 @@ impl Config {
     pub fn set_background(&mut self, background: Color) {
         self.background = background;
     }
+
+    /// Set the config's max depth.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Set the config's gamma.
+    pub fn set_gamma(&mut self, gamma: f64) {
+        self.gamma = gamma;
+    }
+
+    /// Get the config's environment, replacing the background color.
+    pub fn environment(&self) -> Option<&Arc<dyn Background>> {
+        self.environment.as_ref()
+    }
+
+    /// Set the config's environment, replacing the background color.
+    pub fn set_environment(&mut self, environment: Option<Arc<dyn Background>>) {
+        self.environment = environment;
+    }
+
+    /// Get the config's lights without a surface.
+    pub fn lights(&self) -> &[Arc<dyn Light>] {
+        &self.lights
+    }
+
+    /// Add a light without a surface, it is only reached by shadow rays.
+    pub fn add_light(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    /// Get the config's seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Set the config's seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
 }
 
 impl Default for Config {
@@ -94,49 +149,27 @@ impl Default for Config {
             max_depth: MAX_DEPTH,
             gamma: GAMMA,
             background: Color::zeros(),
+            environment: None,
+            lights: Vec::new(),
+            seed: SEED,
         }
     }
 }
 
-fn ray_color<H: Hittable>(r: &Ray, background: &Color, world: &H, depth: usize) -> Color {
-    // If we've exceeded the ray bounce limit, no more light is gathered.
-    if depth == 0 {
-        return Color::zeros();
-    }
-
-    // If the ray hits nothing, return the background color.
-    let rec = match world.hit(r, 0.001, f64::INFINITY) {
-        Some(rec) => rec,
-        None => return *background,
-    };
-
-    let mat = rec
-        .mat
-        .as_ref()
-        .expect("at this point the rec should have a inner material");
-
-    let emitted = mat.emitted(rec.u, rec.v, &rec.p);
-
-    match mat.scatter(r, &rec) {
-        Some((attenuation, scattered)) => {
-            emitted + attenuation * ray_color(&scattered, background, world, depth - 1)
-        }
-        None => emitted,
-    }
-}
-
 #[cfg(feature = "progressbar")]
 use indicatif::{ParallelProgressIterator, ProgressBar};
 
-struct Runner<'hit, 'conf, 'cam, H: Hittable> {
+struct Runner<'hit, 'conf, 'cam, H: Hittable, I: Integrator + ?Sized> {
     world: &'hit H,
+    lights: &'hit HittableList,
+    integrator: &'hit I,
     conf: &'conf Config,
     cam: &'cam Camera,
     #[cfg(feature = "progressbar")]
     pb: ProgressBar,
 }
 
-impl<'hit, 'conf, 'cam, H: Hittable> Runner<'hit, 'conf, 'cam, H> {
+impl<'hit, 'conf, 'cam, H: Hittable, I: Integrator + ?Sized> Runner<'hit, 'conf, 'cam, H, I> {
     fn irun(&self) -> Vec<Color> {
         cfg_if! {
             if #[cfg(feature = "progressbar")] {
@@ -170,15 +203,31 @@ impl<'hit, 'conf, 'cam, H: Hittable> Runner<'hit, 'conf, 'cam, H> {
             }
         }
 
+        let scene = Scene {
+            world: self.world,
+            lights: self.lights,
+            background: match &self.conf.environment {
+                Some(environment) => &**environment,
+                None => &self.conf.background,
+            },
+            delta_lights: &self.conf.lights,
+            max_depth: self.conf.max_depth,
+        };
+
         let inner = |&j| {
             (0..self.conf.image_width)
                 .map(|i| {
+                    let pixel = (j * self.conf.image_width + i) as u64;
                     let pixel_color = (0..self.conf.samples_per_pixel)
-                        .map(|_| {
+                        .map(|s| {
+                            // Every sample gets its own stream so the result doesn't
+                            // depend on which thread renders which row.
+                            seed_rng(stream_seed(self.conf.seed, pixel, s as u64));
+
                             let v = calc(j, self.conf.image_height);
                             let u = calc(i, self.conf.image_width);
                             let r = self.cam.get_ray(u, v);
-                            ray_color(&r, &self.conf.background, self.world, self.conf.max_depth)
+                            self.integrator.ray_color(&r, &scene)
                         })
                         .reduce(|acc, v| acc + v)
                         .expect("This iteration should never yield a None");
@@ -200,18 +249,134 @@ impl<'hit, 'conf, 'cam, H: Hittable> Runner<'hit, 'conf, 'cam, H> {
     }
 }
 
+/// Renders the world with the given integrator, the `lights` are sampled
+/// directly, usually [`HittableList::lights`] of the world.
 #[cfg(not(feature = "progressbar"))]
-pub fn run<H: Hittable>(world: &H, conf: &Config, cam: &Camera) -> Vec<Color> {
-    Runner { world, conf, cam }.irun()
+pub fn run<H: Hittable, I: Integrator + ?Sized>(
+    world: &H,
+    lights: &HittableList,
+    integrator: &I,
+    conf: &Config,
+    cam: &Camera,
+) -> Vec<Color> {
+    Runner {
+        world,
+        lights,
+        integrator,
+        conf,
+        cam,
+    }
+    .irun()
 }
 
+/// Renders the world with the given integrator, the `lights` are sampled
+/// directly, usually [`HittableList::lights`] of the world.
 #[cfg(feature = "progressbar")]
-pub fn run<H: Hittable>(world: &H, conf: &Config, pb: ProgressBar, cam: &Camera) -> Vec<Color> {
+pub fn run<H: Hittable, I: Integrator + ?Sized>(
+    world: &H,
+    lights: &HittableList,
+    integrator: &I,
+    conf: &Config,
+    pb: ProgressBar,
+    cam: &Camera,
+) -> Vec<Color> {
     Runner {
         world,
+        lights,
+        integrator,
         conf,
         pb,
         cam,
     }
     .irun()
 }
+
+/// Renders the world without reporting any progress, independent of the
+/// `progressbar` feature.
+pub fn run_hidden<H: Hittable, I: Integrator + ?Sized>(
+    world: &H,
+    lights: &HittableList,
+    integrator: &I,
+    conf: &Config,
+    cam: &Camera,
+) -> Vec<Color> {
+    cfg_if! {
+        if #[cfg(feature = "progressbar")] {
+            run(world, lights, integrator, conf, ProgressBar::hidden(), cam)
+        } else {
+            run(world, lights, integrator, conf, cam)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        hittable::HittableList,
+        integrator::PathTracer,
+        material::{Dielectric, Lambertian},
+        objects::Sphere,
+        texture::NoiseTexture,
+    };
+
+    fn render_with_threads(threads: usize, seed: u64) -> Vec<Color> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+
+        pool.install(|| {
+            crate::seed_rng(seed);
+
+            let mut world = HittableList::new();
+            world.add(Sphere::new(
+                [0.0, -100.5, -1.0].into(),
+                100.0,
+                Arc::new(Lambertian::with_texture(NoiseTexture::with_scale(4.0))),
+            ));
+            world.add(Sphere::new(
+                [0.0, 0.0, -1.0].into(),
+                0.5,
+                Arc::new(Dielectric::new(1.5)),
+            ));
+
+            let mut conf = Config::default();
+            conf.set_image_width(24);
+            conf.set_samples_per_pixel(4);
+            conf.set_background([0.7, 0.8, 1.0].into());
+            conf.set_seed(seed);
+
+            let cam = Camera::new(
+                [0.0, 0.0, 1.0].into(),
+                [0.0, 0.0, -1.0].into(),
+                [0.0, 1.0, 0.0].into(),
+                60.0,
+                conf.aspect_ratio(),
+                0.1,
+                2.0,
+                0.0,
+                1.0,
+            );
+
+            run_hidden(
+                &world,
+                &HittableList::new(),
+                &PathTracer::default(),
+                &conf,
+                &cam,
+            )
+        })
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let single = render_with_threads(1, 7);
+        let multi = render_with_threads(4, 7);
+
+        assert!(single == multi, "the thread count changed the image");
+        assert!(single != render_with_threads(4, 8), "the seed is ignored");
+    }
+}
@@ -0,0 +1,322 @@
+//! The Preetham daylight model, "A Practical Analytic Model for Daylight"
+//! by Preetham, Shirley and Smits.
+
+use std::f64::consts::PI;
+
+use crate::{
+    background::Background,
+    clamp, degrees_to_radians,
+    description::{BackgroundDescription, Describer},
+    onb::Onb,
+    rand_range,
+    ray::Vec3,
+    render::Color,
+};
+
+/// Maps the luminance of the model in kcd/m² to the range of the colors used
+/// by the other scenes.
+const LUMINANCE_SCALE: f64 = 0.05;
+
+/// The irradiance of the sun above the atmosphere, in the units of the sky.
+const SUN_IRRADIANCE: f64 = 10.0;
+
+/// The wavelengths in µm the red, green and blue parts of the sun are
+/// attenuated at.
+const WAVELENGTHS: [f64; 3] = [0.65, 0.57, 0.475];
+
+/// The coefficients of the Perez distribution of one component of the sky.
+#[derive(Debug, Clone, Copy)]
+struct Perez([f64; 5]);
+
+impl Perez {
+    fn eval(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.0;
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+/// A clear sky lit by the sun, with the sun disk sampled as a light.
+///
+/// The sky is only found by sampling the materials, as it is too smooth to
+/// gain much from the light sampling.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    /// In degrees above the horizon.
+    sun_elevation: f64,
+    /// In degrees around the y axis, from the x axis towards -z.
+    sun_azimuth: f64,
+    turbidity: f64,
+    intensity: f64,
+    /// The angular diameter of the sun in degrees.
+    sun_size: f64,
+    sun_direction: Vec3,
+    /// The Perez distributions of the luminance Y and the chromaticity x, y.
+    perez: [Perez; 3],
+    /// The values of the components at the zenith, divided by the Perez
+    /// distribution there.
+    zenith: [f64; 3],
+    /// The fraction of the sunlight passing the atmosphere.
+    sun_transmittance: Color,
+}
+
+impl Sky {
+    /// The sun is given in degrees, the turbidity of the air is clamped to
+    /// [1.7, 10], from a very clear to a hazy sky.
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        let t = clamp(turbidity, 1.7, 10.0);
+
+        let elevation = degrees_to_radians(sun_elevation);
+        let azimuth = degrees_to_radians(sun_azimuth);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.sin(),
+        );
+
+        // the model only covers a sun above the horizon
+        let theta_s = PI / 2.0 - elevation.max(0.0);
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let cubic =
+            |c: [f64; 4]| c[0] * theta_s.powi(3) + c[1] * theta_s.powi(2) + c[2] * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let mut zenith = [zenith_luminance * LUMINANCE_SCALE, zenith_x, zenith_y];
+        for (z, p) in zenith.iter_mut().zip(&perez) {
+            *z /= p.eval(1.0, theta_s);
+        }
+
+        Self {
+            sun_elevation,
+            sun_azimuth,
+            turbidity,
+            intensity: 1.0,
+            sun_size: 0.53,
+            sun_direction,
+            perez,
+            zenith,
+            sun_transmittance: sun_transmittance(theta_s, t),
+        }
+    }
+
+    /// Get the elevation of the sun in degrees.
+    pub fn sun_elevation(&self) -> f64 {
+        self.sun_elevation
+    }
+
+    /// Get the azimuth of the sun in degrees.
+    pub fn sun_azimuth(&self) -> f64 {
+        self.sun_azimuth
+    }
+
+    /// Get the turbidity of the air.
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    /// Get the factor the sky and the sun are scaled by.
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// Set the factor the sky and the sun are scaled by.
+    pub fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    /// Get the angular diameter of the sun in degrees.
+    pub fn sun_size(&self) -> f64 {
+        self.sun_size
+    }
+
+    /// Set the angular diameter of the sun in degrees, a larger sun casts
+    /// softer shadows with the same brightness.
+    pub fn set_sun_size(&mut self, sun_size: f64) {
+        self.sun_size = sun_size;
+    }
+
+    fn has_sun(&self) -> bool {
+        self.sun_elevation > 0.0 && self.sun_size > 0.0
+    }
+
+    /// The cosine of the angular radius of the sun.
+    fn cos_sun_radius(&self) -> f64 {
+        degrees_to_radians(self.sun_size / 2.0).cos()
+    }
+
+    fn sun_solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_sun_radius())
+    }
+
+    /// The light of the sky without the sun.
+    fn sky(&self, direction: &Vec3) -> Color {
+        let d = direction.unit_vector();
+        // below the horizon continues the horizon
+        let cos_theta = d.y().max(0.01);
+        let gamma = clamp(Vec3::dot(&d, &self.sun_direction), -1.0, 1.0).acos();
+
+        let [luminance, x, y] =
+            [0, 1, 2].map(|i| self.zenith[i] * self.perez[i].eval(cos_theta, gamma));
+
+        // from xyY over XYZ to linear sRGB
+        let big_x = x * luminance / y;
+        let big_z = (1.0 - x - y) * luminance / y;
+        let rgb = [
+            3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+        ];
+
+        Color::from(rgb.map(|c| c.max(0.0)))
+    }
+}
+
+/// The fraction of the sunlight passing the atmosphere, with Rayleigh
+/// scattering by the air and Ångström's formula for the haze.
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> Color {
+    // Kasten's relative air mass
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    Color::from(WAVELENGTHS.map(|lambda| {
+        let rayleigh = 0.008735 * lambda.powf(-4.08);
+        let aerosol = beta * lambda.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    }))
+}
+
+impl Background for Sky {
+    fn value(&self, direction: &Vec3) -> Color {
+        let mut color = self.sky(direction);
+
+        let cos_sun = Vec3::dot(&direction.unit_vector(), &self.sun_direction);
+        if self.has_sun() && cos_sun >= self.cos_sun_radius() {
+            color += (SUN_IRRADIANCE / self.sun_solid_angle()) * self.sun_transmittance;
+        }
+
+        self.intensity * color
+    }
+
+    fn is_light(&self) -> bool {
+        self.has_sun()
+    }
+
+    /// Only the sun disk is sampled.
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let cos_sun = Vec3::dot(&direction.unit_vector(), &self.sun_direction);
+        if self.has_sun() && cos_sun >= self.cos_sun_radius() {
+            1.0 / self.sun_solid_angle()
+        } else {
+            0.0
+        }
+    }
+
+    /// Picks a direction uniformly within the sun disk.
+    fn random(&self) -> Vec3 {
+        let r1: f64 = rand_range(0.0..1.0);
+        let r2: f64 = rand_range(0.0..1.0);
+        let z = 1.0 + r2 * (self.cos_sun_radius() - 1.0);
+
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+
+        Onb::from_w(&self.sun_direction).local(&local)
+    }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<BackgroundDescription> {
+        Ok(BackgroundDescription::Sky {
+            sun_elevation: self.sun_elevation,
+            sun_azimuth: self.sun_azimuth,
+            turbidity: self.turbidity,
+            intensity: self.intensity,
+            sun_size: self.sun_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sky() {
+        let sky = Sky::new(30.0, 90.0, 3.0);
+
+        let zenith = sky.value(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.z() > zenith.x(), "the sky isn't blue: {:?}", zenith);
+
+        // brighter around the sun
+        let horizon = |x: f64, z: f64| sky.value(&Vec3::new(x, 0.1, z)).y();
+        assert!(horizon(0.0, -1.0) > horizon(0.0, 1.0));
+        assert!(horizon(0.0, 1.0) > 0.0);
+
+        // the evening sun is redder
+        let noon = Sky::new(80.0, 0.0, 3.0);
+        let evening = Sky::new(5.0, 0.0, 3.0);
+        let ratio = |s: &Sky| s.sun_transmittance.x() / s.sun_transmittance.z();
+        assert!(ratio(&evening) > 2.0 * ratio(&noon));
+
+        let night = Sky::new(-10.0, 0.0, 3.0);
+        assert!(!night.is_light());
+        assert_eq!(night.pdf_value(&night.sun_direction), 0.0);
+    }
+
+    #[test]
+    fn test_sun_sampling() {
+        let mut sky = Sky::new(40.0, 120.0, 2.5);
+        sky.set_sun_size(2.0);
+        assert!(sky.is_light());
+
+        let n = 10_000;
+        let mut sum = Color::zeros();
+        for _ in 0..n {
+            let d = sky.random();
+            assert!((d.length() - 1.0).abs() < 1e-9);
+
+            let pdf = sky.pdf_value(&d);
+            assert!((pdf - 1.0 / sky.sun_solid_angle()).abs() < 1e-6);
+            sum += (sky.value(&d) - sky.sky(&d)) / pdf;
+        }
+
+        // the irradiance of the sun doesn't depend on its size
+        let expected = SUN_IRRADIANCE * sky.sun_transmittance;
+        let estimate = sum / n as f64;
+        assert!((estimate - expected).length() < 1e-6 * expected.length());
+
+        let away = -sky.sun_direction;
+        assert_eq!(sky.pdf_value(&away), 0.0);
+        assert_eq!(sky.value(&away), sky.sky(&away));
+    }
+}
@@ -1,8 +1,15 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
 
 use crate::{
     clamp,
+    description::{self, Describer, TextureDescription},
     helpers::loader::{self, ImageHolder},
+    hittable::HitRecord,
     perlin::Perlin,
     ray::Point,
     render::Color,
@@ -10,6 +17,30 @@ use crate::{
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point) -> Color;
+
+    /// The color at a hit, by default the value at its texture coordinates.
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.p)
+    }
+
+    /// A serializable description of the texture.
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        description::not_describable(std::any::type_name::<Self>())
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Color {
+        (**self).value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        (**self).value_at(rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        (**self).describe(d)
+    }
 }
 
 #[derive(Clone)]
@@ -27,6 +58,33 @@ impl Texture for SolidColor {
     fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
         self.color_value
     }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        Ok(TextureDescription::Solid {
+            color: self.color_value.into(),
+        })
+    }
+}
+
+/// The vertex colors of the mesh hit, interpolated across the triangle.
+///
+/// The colors are kept by the mesh, so one texture serves every colored
+/// mesh. Objects without vertex colors are black.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VertexColor;
+
+impl Texture for VertexColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
+        Color::zeros()
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        rec.color.unwrap_or_else(Color::zeros)
+    }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        Ok(TextureDescription::VertexColor)
+    }
 }
 
 pub struct CheckerTexture<O, E> {
@@ -47,6 +105,11 @@ where
     pub fn with_texture(even: E, odd: O) -> Self {
         Self { odd, even }
     }
+
+    fn is_odd(p: &Point) -> bool {
+        let calc = |v: f64| f64::sin(10.0 * v);
+        calc(p.x()) * calc(p.y()) * calc(p.z()) < 0.0
+    }
 }
 
 impl<O, E> Texture for CheckerTexture<O, E>
@@ -55,14 +118,27 @@ where
     E: Texture,
 {
     fn value(&self, u: f64, v: f64, p: &Point) -> Color {
-        let calc = |v: f64| f64::sin(10.0 * v);
-        let sines = calc(p.x()) * calc(p.y()) * calc(p.z());
-        if sines < 0.0 {
+        if Self::is_odd(p) {
             self.odd.value(u, v, p)
         } else {
             self.even.value(u, v, p)
         }
     }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        if Self::is_odd(&rec.p) {
+            self.odd.value_at(rec)
+        } else {
+            self.even.value_at(rec)
+        }
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        Ok(TextureDescription::Checker {
+            even: d.texture(&self.even)?,
+            odd: d.texture(&self.odd)?,
+        })
+    }
 }
 
 pub struct NoiseTexture {
@@ -89,6 +165,10 @@ impl Texture for NoiseTexture {
             * 0.5
             * (1.0 + f64::sin(self.scale * p.z() + 10.0 * self.noise.turb(p)))
     }
+
+    fn describe(&self, _d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        Ok(TextureDescription::Noise { scale: self.scale })
+    }
 }
 
 impl Default for NoiseTexture {
@@ -100,12 +180,22 @@ impl Default for NoiseTexture {
 #[derive(Default, Debug)]
 pub struct ImageTexture {
     img: Option<ImageHolder>,
+    path: Option<PathBuf>,
 }
 
 impl ImageTexture {
     pub fn new<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
         let img = loader::read(path)?;
-        Ok(Self { img: Some(img) })
+        Ok(Self {
+            img: Some(img),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Get a reference to the path the image was loaded from.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
     }
 }
 
@@ -141,4 +231,12 @@ impl Texture for ImageTexture {
             }
         }
     }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<TextureDescription> {
+        let path = self
+            .path()
+            .ok_or_else(|| anyhow!("an image texture without an image can not be described"))?;
+
+        Ok(TextureDescription::Image { path: d.path(path) })
+    }
 }
@@ -0,0 +1,714 @@
+use std::ops;
+
+use crate::{
+    aabb::Aabb,
+    degrees_to_radians,
+    description::{Describer, ObjectDescription},
+    hittable::{HitRecord, Hittable},
+    ray::{Point, Ray, Vec3},
+};
+
+/// A 4x4 matrix in row major order, used for affine transformations of
+/// points, vectors and normals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut res = Self::identity();
+        for i in 0..3 {
+            res.m[i][3] = offset.data()[i];
+        }
+        res
+    }
+
+    pub fn scaling(factor: Vec3) -> Self {
+        let mut res = Self::identity();
+        for i in 0..3 {
+            res.m[i][i] = factor.data()[i];
+        }
+        res
+    }
+
+    /// A rotation by `angle` degrees around the x axis.
+    pub fn rotation_x(angle: f64) -> Self {
+        Self::rotation([1.0, 0.0, 0.0].into(), angle)
+    }
+
+    /// A rotation by `angle` degrees around the y axis.
+    pub fn rotation_y(angle: f64) -> Self {
+        Self::rotation([0.0, 1.0, 0.0].into(), angle)
+    }
+
+    /// A rotation by `angle` degrees around the z axis.
+    pub fn rotation_z(angle: f64) -> Self {
+        Self::rotation([0.0, 0.0, 1.0].into(), angle)
+    }
+
+    /// A counterclockwise rotation by `angle` degrees around the given axis
+    /// through the origin.
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let [x, y, z] = <[f64; 3]>::from(axis.unit_vector());
+        let rads = degrees_to_radians(angle);
+        let (sin, cos) = rads.sin_cos();
+        let t = 1.0 - cos;
+
+        Self::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Get a reference to the rows of the matrix.
+    pub fn data(&self) -> &[[f64; 4]; 4] {
+        &self.m
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut res = [[0.0; 4]; 4];
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = self.m[j][i];
+            }
+        }
+        Self { m: res }
+    }
+
+    /// The inverse of the matrix, `None` if it is singular.
+    ///
+    /// Uses Gauss-Jordan elimination with partial pivoting.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| f64::total_cmp(&a[i][col].abs(), &a[j][col].abs()))
+                .unwrap_or(col);
+
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in (0..4).filter(|&row| row != col) {
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+
+        Some(Self { m: inv })
+    }
+
+    /// The determinant of the linear part, the upper left 3x3 matrix.
+    fn linear_determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Transforms a point, including the translation.
+    pub fn transform_point(&self, p: &Point) -> Point {
+        let m = &self.m;
+        let [x, y, z] = [p.x(), p.y(), p.z()];
+        let calc = |i: usize| m[i][0] * x + m[i][1] * y + m[i][2] * z + m[i][3];
+
+        let w = calc(3);
+        let res = Point::new(calc(0), calc(1), calc(2));
+        if w == 1.0 {
+            res
+        } else {
+            res / w
+        }
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let [x, y, z] = [v.x(), v.y(), v.z()];
+        let calc = |i: usize| m[i][0] * x + m[i][1] * y + m[i][2] * z;
+
+        Vec3::new(calc(0), calc(1), calc(2))
+    }
+
+    /// Transforms a normal by the transpose of the matrix.
+    ///
+    /// Normals have to be transformed by the inverse transpose to stay
+    /// perpendicular to the surface, so this is called on the inverse of the
+    /// matrix used for the points. The result isn't normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let [x, y, z] = [n.x(), n.y(), n.z()];
+        let calc = |i: usize| m[0][i] * x + m[1][i] * y + m[2][i] * z;
+
+        Vec3::new(calc(0), calc(1), calc(2))
+    }
+
+    /// The box surrounding all eight transformed corners of the given box.
+    pub fn transform_box(&self, bbox: &Aabb) -> Aabb {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+
+        for corner in 0..8 {
+            let pick = |axis: usize| {
+                if corner & (1 << axis) == 0 {
+                    bbox.min().data()[axis]
+                } else {
+                    bbox.max().data()[axis]
+                }
+            };
+
+            let p = self.transform_point(&Point::new(pick(0), pick(1), pick(2)));
+            for axis in 0..3 {
+                min[axis] = f64::min(min[axis], p.data()[axis]);
+                max[axis] = f64::max(max[axis], p.data()[axis]);
+            }
+        }
+
+        Aabb::new(min.into(), max.into())
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl From<[[f64; 4]; 4]> for Mat4 {
+    fn from(m: [[f64; 4]; 4]) -> Self {
+        Self::new(m)
+    }
+}
+
+impl From<Mat4> for [[f64; 4]; 4] {
+    fn from(m: Mat4) -> Self {
+        m.m
+    }
+}
+
+impl ops::Mul for Mat4 {
+    type Output = Self;
+
+    /// The transformation applying `rhs` first and then `self`.
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut res = [[0.0; 4]; 4];
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Self { m: res }
+    }
+}
+
+/// An invertible transformation together with its inverse.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    m: Mat4,
+    inv: Mat4,
+}
+
+impl Transform {
+    /// `None` if the matrix isn't invertible.
+    pub fn new(m: Mat4) -> Option<Self> {
+        let inv = m.inverse()?;
+        Some(Self { m, inv })
+    }
+
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            m: Mat4::translation(offset),
+            inv: Mat4::translation(-offset),
+        }
+    }
+
+    /// Panics if one of the factors is zero.
+    pub fn scaling(factor: Vec3) -> Self {
+        assert!(
+            factor.data().iter().all(|&f| f != 0.0),
+            "a scaling by zero can't be inverted"
+        );
+
+        let [x, y, z] = <[f64; 3]>::from(factor);
+        Self {
+            m: Mat4::scaling(factor),
+            inv: Mat4::scaling([1.0 / x, 1.0 / y, 1.0 / z].into()),
+        }
+    }
+
+    /// A rotation by `angle` degrees around the x axis.
+    pub fn rotation_x(angle: f64) -> Self {
+        Self::rotation([1.0, 0.0, 0.0].into(), angle)
+    }
+
+    /// A rotation by `angle` degrees around the y axis.
+    pub fn rotation_y(angle: f64) -> Self {
+        Self::rotation([0.0, 1.0, 0.0].into(), angle)
+    }
+
+    /// A rotation by `angle` degrees around the z axis.
+    pub fn rotation_z(angle: f64) -> Self {
+        Self::rotation([0.0, 0.0, 1.0].into(), angle)
+    }
+
+    /// A rotation by `angle` degrees around the given axis through the origin.
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let m = Mat4::rotation(axis, angle);
+        // the inverse of a rotation is its transpose
+        Self {
+            m,
+            inv: m.transpose(),
+        }
+    }
+
+    /// The rotation described by a quaternion.
+    pub fn from_quat(q: &Quat) -> Self {
+        let m = q.to_mat4();
+        Self {
+            m,
+            inv: m.transpose(),
+        }
+    }
+
+    /// The transformation applying `self` first and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        Self {
+            m: next.m * self.m,
+            inv: self.inv * next.inv,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    /// Get a reference to the transformation's matrix.
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    /// Get a reference to the matrix of the inverse transformation.
+    pub fn inverse_matrix(&self) -> &Mat4 {
+        &self.inv
+    }
+
+    pub fn transform_point(&self, p: &Point) -> Point {
+        self.m.transform_point(p)
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        self.m.transform_vector(v)
+    }
+
+    /// Transforms a normal by the inverse transpose, the result isn't
+    /// normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        self.inv.transform_normal(n)
+    }
+
+    pub fn transform_box(&self, bbox: &Aabb) -> Aabb {
+        self.m.transform_box(bbox)
+    }
+
+    /// Transforms a ray, keeping the parameter `t` of every point on it.
+    pub fn transform_ray(&self, r: &Ray) -> Ray {
+        Ray::with_time(
+            self.transform_point(r.origin()),
+            self.transform_vector(r.direction()),
+            r.time(),
+        )
+    }
+
+    /// The [`Hittable::pdf_value`] of an object in the local space of the
+    /// transformation, seen from `origin` in the space of the
+    /// transformation.
+    ///
+    /// Transforming directions changes their solid angle, the density is
+    /// scaled by the determinant of the inverse over the cubed length of the
+    /// transformed unit direction.
+    pub(crate) fn pdf_value<H: Hittable + ?Sized>(
+        &self,
+        ptr: &H,
+        origin: &Point,
+        direction: &Vec3,
+    ) -> f64 {
+        let local_origin = self.inv.transform_point(origin);
+        let local = self.inv.transform_vector(&direction.unit_vector());
+
+        let pdf = ptr.pdf_value(&local_origin, &local);
+        pdf * self.inv.linear_determinant().abs() / local.length().powi(3)
+    }
+
+    /// The [`Hittable::random`] of an object in the local space of the
+    /// transformation, moved into the space of the transformation.
+    pub(crate) fn random<H: Hittable + ?Sized>(&self, ptr: &H, origin: &Point) -> Vec3 {
+        let local = ptr.random(&self.inv.transform_point(origin));
+        self.transform_vector(&local)
+    }
+
+    /// Moves a record of a hit in the local space of an object into the
+    /// space of the transformation.
+    pub(crate) fn transform_record(&self, rec: &mut HitRecord) {
+        rec.p = self.transform_point(&rec.p);
+        // still faces against the ray, as the sign of the dot product is kept
+        rec.normal = self.transform_normal(&rec.normal).unit_vector();
+    }
+}
+
+impl ops::Mul for Transform {
+    type Output = Self;
+
+    /// The transformation applying `rhs` first and then `self`.
+    fn mul(self, rhs: Self) -> Self::Output {
+        rhs.then(&self)
+    }
+}
+
+/// A unit quaternion describing a rotation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    w: f64,
+    v: Vec3,
+}
+
+impl Quat {
+    /// Normalizes the quaternion `w + xi + yj + zk`.
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        let len = (w * w + x * x + y * y + z * z).sqrt();
+        Self {
+            w: w / len,
+            v: Vec3::new(x, y, z) / len,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// A counterclockwise rotation by `angle` degrees around the given axis.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let half = degrees_to_radians(angle) / 2.0;
+        let (sin, cos) = half.sin_cos();
+        let v = axis.unit_vector() * sin;
+        Self::new(cos, v.x(), v.y(), v.z())
+    }
+
+    pub fn dot(&self, rhs: &Self) -> f64 {
+        self.w * rhs.w + Vec3::dot(&self.v, &rhs.v)
+    }
+
+    /// The angle in radians of the rotation taking `self` to `rhs` along the
+    /// shortest path.
+    pub fn angle(&self, rhs: &Self) -> f64 {
+        2.0 * f64::min(self.dot(rhs).abs(), 1.0).acos()
+    }
+
+    /// Spherical linear interpolation, rotating with a constant angular
+    /// velocity along the shortest path.
+    pub fn slerp(&self, rhs: &Self, t: f64) -> Self {
+        let mut cos = self.dot(rhs);
+        // q and -q describe the same rotation
+        let rhs = if cos < 0.0 {
+            cos = -cos;
+            Self {
+                w: -rhs.w,
+                v: -rhs.v,
+            }
+        } else {
+            *rhs
+        };
+
+        let (a, b) = if cos > 0.9995 {
+            // close enough for a linear interpolation, avoids dividing by ~0
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        let v = a * self.v + b * rhs.v;
+        Self::new(a * self.w + b * rhs.w, v.x(), v.y(), v.z())
+    }
+
+    pub fn to_mat4(&self) -> Mat4 {
+        let w = self.w;
+        let [x, y, z] = <[f64; 3]>::from(self.v);
+
+        Mat4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl From<Quat> for [f64; 4] {
+    fn from(q: Quat) -> Self {
+        [q.w, q.v.x(), q.v.y(), q.v.z()]
+    }
+}
+
+impl From<[f64; 4]> for Quat {
+    fn from([w, x, y, z]: [f64; 4]) -> Self {
+        Self::new(w, x, y, z)
+    }
+}
+
+/// An object placed by an arbitrary affine transformation.
+///
+/// Unlike nesting [`Translate`](crate::hittable::Translate) and
+/// [`RotateY`](crate::hittable::RotateY), this supports rotations around any
+/// axis and non uniform scaling, and combines any number of them into a
+/// single matrix.
+pub struct Transformed<H> {
+    ptr: H,
+    transform: Transform,
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(ptr: H, transform: Transform) -> Self {
+        Self { ptr, transform }
+    }
+
+    /// Get a reference to the object's transformation.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl<H> Hittable for Transformed<H>
+where
+    H: Hittable,
+{
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = self.ptr.bounding_box(time0, time1)?;
+        Some(self.transform.transform_box(&bbox))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let local = self.transform.inverse().transform_ray(r);
+
+        let mut rec = self.ptr.hit(&local, t_min, t_max)?;
+        self.transform.transform_record(&mut rec);
+
+        Some(rec)
+    }
+
+    fn describe(&self, d: &mut Describer) -> anyhow::Result<ObjectDescription> {
+        Ok(ObjectDescription::Transform {
+            matrix: (*self.transform.matrix()).into(),
+            object: Box::new(self.ptr.describe(d)?),
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.ptr.is_light()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Vec3) -> f64 {
+        self.transform.pdf_value(&self.ptr, origin, direction)
+    }
+
+    fn random(&self, origin: &Point) -> Vec3 {
+        self.transform.random(&self.ptr, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Mat4, b: &Mat4) {
+        for (ra, rb) in a.data().iter().zip(b.data()) {
+            for (va, vb) in ra.iter().zip(rb) {
+                assert!((va - vb).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat4::translation([1.0, -2.0, 3.0].into()) * Mat4::scaling([2.0, 0.5, 4.0].into());
+        let inv = m.inverse().unwrap();
+
+        assert_near(&(m * inv), &Mat4::identity());
+        assert_near(&(inv * m), &Mat4::identity());
+
+        let p = Point::new(0.3, 0.7, -1.1);
+        let back = inv.transform_point(&m.transform_point(&p));
+        assert!((back - p).near_zero());
+
+        assert!(Mat4::scaling([1.0, 0.0, 1.0].into()).inverse().is_none());
+    }
+
+    #[test]
+    fn test_normal() {
+        let m = Mat4::scaling([4.0, 1.0, 1.0].into());
+        let inv = m.inverse().unwrap();
+
+        // a plane tilted by 45 degrees gets flatter when stretched along x
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let normal = Vec3::new(1.0, -1.0, 0.0);
+
+        let tangent = m.transform_vector(&tangent);
+        let normal = inv.transform_normal(&normal);
+        assert!(Vec3::dot(&tangent, &normal).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let p = Point::new(1.0, 0.0, 0.0);
+        let rotated = Mat4::rotation_z(90.0).transform_point(&p);
+        assert!((rotated - Point::new(0.0, 1.0, 0.0)).near_zero());
+
+        let rotated = Mat4::rotation_y(90.0).transform_point(&p);
+        assert!((rotated - Point::new(0.0, 0.0, -1.0)).near_zero());
+
+        // a third of a turn around the diagonal cycles the axes
+        let rotated = Mat4::rotation([1.0, 1.0, 1.0].into(), 120.0).transform_point(&p);
+        assert!((rotated - Point::new(0.0, 1.0, 0.0)).near_zero());
+
+        let t = Transform::rotation([1.0, 2.0, 3.0].into(), 33.0);
+        assert_near(&(*t.matrix() * *t.inverse_matrix()), &Mat4::identity());
+    }
+
+    #[test]
+    fn test_compose() {
+        let t = Transform::scaling([2.0, 1.0, 1.0].into())
+            .then(&Transform::rotation_z(90.0))
+            .then(&Transform::translation([0.0, 0.0, 5.0].into()));
+
+        let p = t.transform_point(&Point::new(1.0, 0.0, 0.0));
+        assert!((p - Point::new(0.0, 2.0, 5.0)).near_zero());
+
+        let back = t.inverse().transform_point(&p);
+        assert!((back - Point::new(1.0, 0.0, 0.0)).near_zero());
+
+        let m = Transform::new(*t.matrix()).unwrap();
+        assert_near(m.inverse_matrix(), t.inverse_matrix());
+    }
+
+    #[test]
+    fn test_transformed() {
+        use crate::{material::Lambertian, objects::Cube};
+        use std::sync::Arc;
+
+        let mat = Arc::new(Lambertian::new([0.5, 0.5, 0.5].into()));
+        let cube = Cube::new(&Point::zeros(), &Point::ones(), mat);
+
+        // a box stretched to 2x1x3 and tipped over around x
+        let transform =
+            Transform::scaling([2.0, 1.0, 3.0].into()).then(&Transform::rotation_x(90.0));
+        let obj = Transformed::new(cube, transform);
+
+        let bbox = obj.bounding_box(0.0, 1.0).unwrap();
+        assert!((*bbox.min() - Point::new(0.0, -3.0, 0.0)).near_zero());
+        assert!((*bbox.max() - Point::new(2.0, 0.0, 1.0)).near_zero());
+
+        let r = Ray::new(Point::new(1.0, -1.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = obj.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 9.0).abs() < 1e-9);
+        assert!((rec.p - Point::new(1.0, -1.0, 1.0)).near_zero());
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).near_zero());
+    }
+
+    #[test]
+    fn test_quat() {
+        let axis = Vec3::new(1.0, 2.0, 3.0);
+        let q = Quat::from_axis_angle(axis, 70.0);
+        assert_near(&q.to_mat4(), &Mat4::rotation(axis, 70.0));
+
+        let start = Quat::identity();
+        let half = start.slerp(&q, 0.5).to_mat4();
+        assert_near(&half, &Mat4::rotation(axis, 35.0));
+        assert!((start.angle(&q) - degrees_to_radians(70.0)).abs() < 1e-9);
+
+        // the shortest path is taken even if the signs differ
+        let neg = Quat::from(<[f64; 4]>::from(q).map(|v| -v));
+        assert_near(&start.slerp(&neg, 0.5).to_mat4(), &half);
+    }
+
+    #[test]
+    fn test_box() {
+        let bbox = Aabb::new([1.0, 1.0, 1.0].into(), [2.0, 3.0, 4.0].into());
+        let m = Mat4::translation([1.0, 0.0, 0.0].into()) * Mat4::scaling([-1.0, 2.0, 1.0].into());
+
+        let res = m.transform_box(&bbox);
+        assert_eq!(res.min(), &Point::new(-1.0, 2.0, 1.0));
+        assert_eq!(res.max(), &Point::new(0.0, 6.0, 4.0));
+    }
+}
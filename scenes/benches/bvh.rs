@@ -0,0 +1,102 @@
+//! Compares the rays per second of the bvh split methods and layouts on the
+//! built-in worlds, run with `cargo bench -p scenes --bench bvh`.
+
+use std::{env, path::Path, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ray_tracing::{
+    bvh::{BvhNode, LinearBvh, SplitMethod},
+    hittable::{Hittable, HittableList},
+    material::Lambertian,
+    objects::Sphere,
+    rand_range,
+    ray::{Point, Ray},
+};
+use scenes::{scenes::Worlds, WorldSettings};
+
+const RAYS_PER_SIDE: usize = 64;
+
+/// The primary rays of a small image of the world.
+fn camera_rays(settings: &WorldSettings) -> Vec<Ray> {
+    let calc = |i| (i as f64 + rand_range(0.0..1.0)) / RAYS_PER_SIDE as f64;
+
+    (0..RAYS_PER_SIDE * RAYS_PER_SIDE)
+        .map(|i| {
+            let (x, y) = (i % RAYS_PER_SIDE, i / RAYS_PER_SIDE);
+            settings.cam.get_ray(calc(x), calc(y))
+        })
+        .collect()
+}
+
+/// The 1000 spheres of the final scene, without the surrounding objects.
+fn clustered_spheres() -> HittableList {
+    let white = Arc::new(Lambertian::new([0.73, 0.73, 0.73].into()));
+    let mut spheres = HittableList::with_capacity(1000);
+    for _ in 0..1000 {
+        spheres.add(Sphere::new(
+            Point::random_range(0.0..165.0),
+            10.0,
+            white.clone(),
+        ));
+    }
+    spheres
+}
+
+fn bench_split(c: &mut Criterion) {
+    // the scenes load their assets relative to the workspace root
+    env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(".."))
+        .expect("unable to change into the workspace root");
+    ray_tracing::seed_rng(0);
+
+    let mut cases = Vec::new();
+    for (name, chosen) in [
+        ("random_scene", Worlds::RandomScene),
+        ("cornell_box", Worlds::CornellBox),
+        ("final_scene", Worlds::FinalScene),
+    ] {
+        let settings = scenes::setup(chosen).expect("unable to setup the scene");
+        let rays = camera_rays(&settings);
+        cases.push((name, settings.world, rays));
+    }
+
+    let spheres = clustered_spheres();
+    let rays = (0..RAYS_PER_SIDE * RAYS_PER_SIDE)
+        .map(|_| {
+            let origin = Point::random_range(-200.0..400.0);
+            let target = Point::random_range(0.0..165.0);
+            Ray::new(origin, target - origin)
+        })
+        .collect();
+    cases.push(("clustered_spheres", spheres, rays));
+
+    let mut group = c.benchmark_group("bvh");
+    for (name, world, rays) in &cases {
+        group.throughput(Throughput::Elements(rays.len() as u64));
+
+        for split in [SplitMethod::Median, SplitMethod::Sah] {
+            let trees: [(_, Box<dyn Hittable>); 2] = [
+                ("", Box::new(BvhNode::with_split(world, 0.0, 1.0, split))),
+                (
+                    "Linear",
+                    Box::new(LinearBvh::with_split(world, 0.0, 1.0, split)),
+                ),
+            ];
+
+            for (kind, bvh) in &trees {
+                let id = BenchmarkId::new(format!("{}{:?}", kind, split), name);
+
+                group.bench_with_input(id, rays, |b, rays| {
+                    b.iter(|| {
+                        rays.iter()
+                            .filter(|r| bvh.hit(r, 0.001, f64::INFINITY).is_some())
+                            .count()
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_split);
+criterion_main!(benches);
@@ -0,0 +1,871 @@
+//! A declarative, toml based, description of a scene.
+//!
+//! ```toml
+//! [config]
+//! image_width = 600
+//! aspect_ratio = 1.0
+//! background = [0.0, 0.0, 0.0]
+//!
+//! [camera]
+//! lookfrom = [278.0, 278.0, -800.0]
+//! lookat = [278.0, 278.0, 0.0]
+//! vfov = 40.0
+//!
+//! [materials.light]
+//! type = "diffuse_light"
+//! emit = [15.0, 15.0, 15.0]
+//!
+//! [[objects]]
+//! type = "xz_rect"
+//! x = [213.0, 343.0]
+//! z = [227.0, 332.0]
+//! k = 554.0
+//! material = "light"
+//! ```
+//!
+//! An `[config.environment]` table replaces the background color by an
+//! environment map or the sky lighting the scene.
+//!
+//! Textures and materials are named and referenced by their name, textures
+//! can also be given inline as a color. Objects placed by instances are
+//! named in the `[shared]` table, each name is created once. Relative paths are resolved relative
+//! to the scene file.
+//!
+//! The format itself is defined in [`ray_tracing::description`], a loaded
+//! or built-in world can be written back with [`save`].
+
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use anyhow::{anyhow, bail, Context};
+pub use ray_tracing::description::*;
+use ray_tracing::{
+    animation::{Animated, AnimatedTransform, Keyframe},
+    background::Background,
+    bvh::LinearBvh,
+    environment::EnvironmentMap,
+    hittable::{HittableList, HittableObject, Rotate, RotateX, RotateY, RotateZ, Translate},
+    instance::Instance,
+    light::{DirectionalLight, Light, PointLight, SpotLight},
+    material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Mat, Metal},
+    medium,
+    mesh::{self, MeshBuffers, TriangleMesh},
+    objects::{rect, Cube, MovingSphere, Sphere, Triangle},
+    profile::{Goniometric, Profile},
+    sky::Sky,
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture, VertexColor},
+    transform::{Transform, Transformed},
+    Config,
+};
+
+use crate::WorldSettings;
+
+/// Loads a scene description file.
+pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<WorldSettings> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)
+        .with_context(|| format!("unable to read scene file {}", path.display()))?;
+
+    parse(&src, path.parent().unwrap_or_else(|| Path::new("")))
+        .with_context(|| format!("unable to load scene file {}", path.display()))
+}
+
+/// Parses a scene description, relative paths are resolved relative to `base`.
+pub fn parse(src: &str, base: &Path) -> anyhow::Result<WorldSettings> {
+    let desc: SceneDescription = toml::from_str(src)?;
+    build(&desc, base)
+}
+
+/// Creates the world described by `desc`.
+pub fn build(desc: &SceneDescription, base: &Path) -> anyhow::Result<WorldSettings> {
+    let mut conf = build_config(&desc.config, base)?;
+    for light in &desc.lights {
+        conf.add_light(build_light(light));
+    }
+
+    // Random textures (noise) are created from the seed as well.
+    if let Some(seed) = desc.config.seed {
+        ray_tracing::seed_rng(seed);
+    }
+
+    let cam = desc.camera.build(conf.aspect_ratio());
+
+    let mut builder = Builder {
+        base,
+        desc,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        shared: HashMap::new(),
+        visiting: Vec::new(),
+        visiting_shared: Vec::new(),
+    };
+
+    let mut world = HittableList::with_capacity(desc.objects.len());
+    for (i, obj) in desc.objects.iter().enumerate() {
+        let obj = builder
+            .object(obj)
+            .with_context(|| format!("unable to create object {}", i))?;
+        world.add_arc(obj);
+    }
+
+    Ok(WorldSettings {
+        conf,
+        lights: world.lights(),
+        world,
+        cam,
+    })
+}
+
+fn build_config(desc: &ConfigDescription, base: &Path) -> anyhow::Result<Config> {
+    let mut conf = Config::default();
+
+    if let Some(aspect_ratio) = desc.aspect_ratio {
+        conf.set_aspect_ratio(aspect_ratio);
+    }
+    if let Some(image_width) = desc.image_width {
+        conf.set_image_width(image_width);
+    }
+    if let Some(samples_per_pixel) = desc.samples_per_pixel {
+        conf.set_samples_per_pixel(samples_per_pixel);
+    }
+    if let Some(max_depth) = desc.max_depth {
+        conf.set_max_depth(max_depth);
+    }
+    if let Some(gamma) = desc.gamma {
+        conf.set_gamma(gamma);
+    }
+    if let Some(background) = desc.background {
+        conf.set_background(background.into());
+    }
+    if let Some(seed) = desc.seed {
+        conf.set_seed(seed);
+    }
+    if let Some(environment) = &desc.environment {
+        conf.set_environment(Some(build_background(environment, base)?));
+    }
+
+    Ok(conf)
+}
+
+fn build_background(
+    desc: &BackgroundDescription,
+    base: &Path,
+) -> anyhow::Result<Arc<dyn Background>> {
+    let background: Arc<dyn Background> = match desc {
+        BackgroundDescription::EnvironmentMap {
+            path,
+            rotation,
+            intensity,
+        } => {
+            let path = base.join(path);
+            let mut env = EnvironmentMap::load(&path)
+                .with_context(|| format!("unable to load environment map {}", path.display()))?;
+            env.set_rotation(*rotation);
+            env.set_intensity(*intensity);
+            Arc::new(env)
+        }
+        BackgroundDescription::Sky {
+            sun_elevation,
+            sun_azimuth,
+            turbidity,
+            intensity,
+            sun_size,
+        } => {
+            let mut sky = Sky::new(*sun_elevation, *sun_azimuth, *turbidity);
+            sky.set_intensity(*intensity);
+            sky.set_sun_size(*sun_size);
+            Arc::new(sky)
+        }
+    };
+    Ok(background)
+}
+
+fn build_light(desc: &LightDescription) -> Arc<dyn Light> {
+    match *desc {
+        LightDescription::Point {
+            position,
+            intensity,
+        } => Arc::new(PointLight::new(position.into(), intensity.into())),
+        LightDescription::Spot {
+            position,
+            direction,
+            intensity,
+            cone_angle,
+            falloff_angle,
+        } => Arc::new(SpotLight::new(
+            position.into(),
+            direction.into(),
+            intensity.into(),
+            cone_angle,
+            falloff_angle,
+        )),
+        LightDescription::Directional {
+            direction,
+            irradiance,
+        } => Arc::new(DirectionalLight::new(direction.into(), irradiance.into())),
+    }
+}
+
+/// Describes the world, paths are written relative to `base`.
+pub fn export(settings: &WorldSettings, base: &Path) -> anyhow::Result<SceneDescription> {
+    let mut describer = Describer::new(base);
+
+    let objects = settings
+        .world
+        .objects()
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| {
+            describer
+                .object(&**obj)
+                .with_context(|| format!("unable to describe object {}", i))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let lights = settings
+        .conf
+        .lights()
+        .iter()
+        .enumerate()
+        .map(|(i, light)| {
+            light
+                .describe()
+                .with_context(|| format!("unable to describe light {}", i))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let config = describe_config(&settings.conf, &mut describer)?;
+    let (textures, materials, shared) = describer.finish();
+
+    Ok(SceneDescription {
+        config,
+        camera: settings.cam.describe(),
+        textures,
+        materials,
+        shared,
+        objects,
+        lights,
+    })
+}
+
+/// Writes the world to a scene description file.
+pub fn save<P: AsRef<Path>>(settings: &WorldSettings, path: P) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let desc = export(settings, path.parent().unwrap_or_else(|| Path::new("")))?;
+    let src = toml::to_string(&desc).context("unable to serialize the scene")?;
+
+    fs::write(path, src).with_context(|| format!("unable to write scene file {}", path.display()))
+}
+
+fn describe_config(conf: &Config, describer: &mut Describer) -> anyhow::Result<ConfigDescription> {
+    let environment = conf
+        .environment()
+        .map(|environment| environment.describe(describer))
+        .transpose()
+        .context("unable to describe the environment")?;
+
+    Ok(ConfigDescription {
+        image_width: Some(conf.image_width()),
+        aspect_ratio: Some(conf.aspect_ratio()),
+        samples_per_pixel: Some(*conf.samples_per_pixel()),
+        max_depth: Some(*conf.max_depth()),
+        gamma: Some(*conf.gamma()),
+        background: Some((*conf.background()).into()),
+        seed: Some(conf.seed()),
+        environment,
+    })
+}
+
+type Tex = Arc<dyn Texture>;
+
+struct Builder<'a> {
+    base: &'a Path,
+    desc: &'a SceneDescription,
+    textures: HashMap<&'a str, Tex>,
+    materials: HashMap<&'a str, Mat>,
+    shared: HashMap<&'a str, HittableObject>,
+    /// The named textures currently being created, used to detect cycles.
+    visiting: Vec<&'a str>,
+    /// The shared objects currently being created.
+    visiting_shared: Vec<&'a str>,
+}
+
+impl<'a> Builder<'a> {
+    fn texture(&mut self, texture: &'a TextureRef) -> anyhow::Result<Tex> {
+        let name = match texture {
+            TextureRef::Color(c) => return Ok(Arc::new(SolidColor::new((*c).into()))),
+            TextureRef::Named(name) => name.as_str(),
+        };
+
+        if let Some(tex) = self.textures.get(name) {
+            return Ok(tex.clone());
+        }
+
+        if self.visiting.contains(&name) {
+            bail!("texture {:?} references itself", name);
+        }
+
+        let desc = self
+            .desc
+            .textures
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown texture {:?}", name))?;
+
+        self.visiting.push(name);
+        let tex = self
+            .build_texture(desc)
+            .with_context(|| format!("unable to create texture {:?}", name))?;
+        self.visiting.pop();
+
+        self.textures.insert(name, tex.clone());
+        Ok(tex)
+    }
+
+    fn build_texture(&mut self, desc: &'a TextureDescription) -> anyhow::Result<Tex> {
+        let tex: Tex = match desc {
+            TextureDescription::Solid { color } => Arc::new(SolidColor::new((*color).into())),
+            TextureDescription::Checker { even, odd } => {
+                let even = self.texture(even)?;
+                let odd = self.texture(odd)?;
+                Arc::new(CheckerTexture::with_texture(even, odd))
+            }
+            TextureDescription::Noise { scale } => Arc::new(NoiseTexture::with_scale(*scale)),
+            TextureDescription::Image { path } => {
+                let path = self.base.join(path);
+                let tex = ImageTexture::new(&path)
+                    .with_context(|| format!("unable to load image {}", path.display()))?;
+                Arc::new(tex)
+            }
+            TextureDescription::VertexColor => Arc::new(VertexColor),
+        };
+        Ok(tex)
+    }
+
+    fn shared(&mut self, name: &'a str) -> anyhow::Result<HittableObject> {
+        if let Some(obj) = self.shared.get(name) {
+            return Ok(obj.clone());
+        }
+
+        if self.visiting_shared.contains(&name) {
+            bail!("shared object {:?} references itself", name);
+        }
+
+        let desc = self
+            .desc
+            .shared
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown shared object {:?}", name))?;
+
+        self.visiting_shared.push(name);
+        let obj = self
+            .object(desc)
+            .with_context(|| format!("unable to create shared object {:?}", name))?;
+        self.visiting_shared.pop();
+
+        self.shared.insert(name, obj.clone());
+        Ok(obj)
+    }
+
+    fn material(&mut self, name: &'a str) -> anyhow::Result<Mat> {
+        if let Some(mat) = self.materials.get(name) {
+            return Ok(mat.clone());
+        }
+
+        let desc = self
+            .desc
+            .materials
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown material {:?}", name))?;
+
+        let mat: Mat = match desc {
+            MaterialDescription::Lambertian { albedo } => {
+                Arc::new(Lambertian::with_texture(self.texture(albedo)?))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Arc::new(Metal::new((*albedo).into(), *fuzz))
+            }
+            MaterialDescription::Dielectric { ir } => Arc::new(Dielectric::new(*ir)),
+            MaterialDescription::DiffuseLight {
+                emit,
+                sides,
+                profile,
+            } => {
+                let mut light = DiffuseLight::with_texture(self.texture(emit)?);
+                light.set_sides(*sides);
+                light.set_profile(profile.as_ref().map(|p| self.profile(p)).transpose()?);
+                Arc::new(light)
+            }
+            MaterialDescription::Isotropic { albedo } => {
+                Arc::new(Isotropic::with_texture(self.texture(albedo)?))
+            }
+        };
+
+        self.materials.insert(name, mat.clone());
+        Ok(mat)
+    }
+
+    fn profile(&self, desc: &ProfileDescription) -> anyhow::Result<Profile> {
+        let profile = match desc {
+            ProfileDescription::Spot {
+                cone_angle,
+                falloff_angle,
+            } => Profile::Spot {
+                cone_angle: *cone_angle,
+                falloff_angle: *falloff_angle,
+            },
+            ProfileDescription::Ies { path } => {
+                Profile::Goniometric(Goniometric::load(self.base.join(path))?)
+            }
+        };
+        Ok(profile)
+    }
+
+    fn list(&mut self, objects: &'a [ObjectDescription]) -> anyhow::Result<HittableList> {
+        let mut list = HittableList::with_capacity(objects.len());
+        for obj in objects {
+            list.add_arc(self.object(obj)?);
+        }
+        Ok(list)
+    }
+
+    fn object(&mut self, desc: &'a ObjectDescription) -> anyhow::Result<HittableObject> {
+        let obj: HittableObject = match desc {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(
+                (*center).into(),
+                *radius,
+                self.material(material)?,
+            )),
+            ObjectDescription::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Arc::new(MovingSphere::new(
+                ((*center0).into(), (*center1).into()),
+                (*time0, *time1),
+                *radius,
+                self.material(material)?,
+            )),
+            ObjectDescription::Cube { min, max, material } => Arc::new(Cube::new(
+                &(*min).into(),
+                &(*max).into(),
+                self.material(material)?,
+            )),
+            ObjectDescription::XyRect { x, y, k, material } => Arc::new(rect::XY::new(
+                self.material(material)?,
+                (x[0], x[1]),
+                (y[0], y[1]),
+                *k,
+            )),
+            ObjectDescription::XzRect { x, z, k, material } => Arc::new(rect::XZ::new(
+                self.material(material)?,
+                (x[0], x[1]),
+                (z[0], z[1]),
+                *k,
+            )),
+            ObjectDescription::YzRect { y, z, k, material } => Arc::new(rect::YZ::new(
+                self.material(material)?,
+                (y[0], y[1]),
+                (z[0], z[1]),
+                *k,
+            )),
+            ObjectDescription::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material,
+            } => {
+                let mut tri = Triangle::new(vertices.map(Into::into), self.material(material)?);
+                if let Some(normals) = normals {
+                    tri = tri.with_normals(normals.map(Into::into));
+                }
+                if let Some(uvs) = uvs {
+                    tri = tri.with_uvs(uvs.map(|[u, v]| (u, v)));
+                }
+                Arc::new(tri)
+            }
+            ObjectDescription::Mesh {
+                positions,
+                normals,
+                uvs,
+                colors,
+                indices,
+                material,
+            } => {
+                let positions = positions.iter().map(|&p| p.into()).collect();
+                let normals = normals
+                    .as_ref()
+                    .map(|n| n.iter().map(|&n| n.into()).collect());
+                let mat = self.material(material)?;
+
+                match (uvs, colors) {
+                    (Some(_), Some(_)) => {
+                        bail!("a mesh can't have both texture coordinates and vertex colors")
+                    }
+                    (_, Some(colors)) => Arc::new(TriangleMesh::with_vertex_colors(
+                        positions,
+                        normals,
+                        colors.iter().map(|&c| c.into()).collect(),
+                        indices.clone(),
+                        mat,
+                    )?),
+                    (uvs, None) => Arc::new(TriangleMesh::with_attributes(
+                        positions,
+                        normals,
+                        uvs.as_ref()
+                            .map(|uv| uv.iter().map(|&[u, v]| (u, v)).collect()),
+                        indices.clone(),
+                        mat,
+                    )?),
+                }
+            }
+            ObjectDescription::Obj { path } => Arc::new(mesh::load_obj(self.base.join(path))?),
+            ObjectDescription::Ply { path, material } => {
+                let buffers = MeshBuffers::read_ply(self.base.join(path))?;
+                let mesh = match material {
+                    Some(material) => buffers.into_mesh(self.material(material)?)?,
+                    None => buffers
+                        .into_colored_mesh(Arc::new(Lambertian::with_texture(VertexColor)))?,
+                };
+                Arc::new(mesh)
+            }
+            ObjectDescription::Stl { path, material } => {
+                let buffers = MeshBuffers::read_stl(self.base.join(path))?;
+                Arc::new(buffers.into_mesh(self.material(material)?)?)
+            }
+            ObjectDescription::List { objects } => Arc::new(self.list(objects)?),
+            ObjectDescription::Bvh { objects } => {
+                let list = self.list(objects)?;
+                if list.objects().is_empty() {
+                    bail!("a bvh needs at least one object");
+                }
+                let cam = &self.desc.camera;
+                Arc::new(LinearBvh::new(&list, cam.time0, cam.time1))
+            }
+            ObjectDescription::Translate { offset, object } => {
+                Arc::new(Translate::new(self.object(object)?, (*offset).into()))
+            }
+            ObjectDescription::RotateX { angle, object } => {
+                Arc::new(RotateX::new(self.object(object)?, *angle))
+            }
+            ObjectDescription::RotateY { angle, object } => {
+                Arc::new(RotateY::new(self.object(object)?, *angle))
+            }
+            ObjectDescription::RotateZ { angle, object } => {
+                Arc::new(RotateZ::new(self.object(object)?, *angle))
+            }
+            ObjectDescription::Rotate {
+                axis,
+                angle,
+                object,
+            } => {
+                if *axis == [0.0; 3] {
+                    bail!("the axis of a rotation can't be zero");
+                }
+                Arc::new(Rotate::new(self.object(object)?, (*axis).into(), *angle))
+            }
+            ObjectDescription::Transform { matrix, object } => {
+                let transform = Transform::new((*matrix).into())
+                    .ok_or_else(|| anyhow!("the transformation {:?} isn't invertible", matrix))?;
+                Arc::new(Transformed::new(self.object(object)?, transform))
+            }
+            ObjectDescription::Instance {
+                matrix,
+                material,
+                object,
+            } => {
+                let transform = Transform::new((*matrix).into())
+                    .ok_or_else(|| anyhow!("the transformation {:?} isn't invertible", matrix))?;
+                let mut instance = Instance::with_transform(self.shared(object)?, transform);
+                instance.set_material(material.as_deref().map(|m| self.material(m)).transpose()?);
+                Arc::new(instance)
+            }
+            ObjectDescription::Animated { keyframes, object } => {
+                let keyframes = keyframes
+                    .iter()
+                    .map(|k| {
+                        Keyframe::new(
+                            k.time,
+                            k.translation.into(),
+                            k.rotation.into(),
+                            k.scale.into(),
+                        )
+                    })
+                    .collect();
+                Arc::new(Animated::new(
+                    self.object(object)?,
+                    AnimatedTransform::try_new(keyframes)?,
+                ))
+            }
+            ObjectDescription::ConstantMedium {
+                density,
+                albedo,
+                boundary,
+            } => Arc::new(medium::Constant::with_texture(
+                self.object(boundary)?,
+                *density,
+                self.texture(albedo)?,
+            )),
+        };
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ArgEnum;
+    use ray_tracing::render::Color;
+
+    use super::*;
+    use crate::scenes::Worlds;
+
+    const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/scenes");
+
+    #[test]
+    fn test_load_example() -> anyhow::Result<()> {
+        let settings = load(Path::new(ASSETS).join("cornell_box_smoke.toml"))?;
+
+        assert_eq!(settings.conf.image_width(), 600);
+        assert_eq!(settings.conf.image_height(), 600);
+        assert_eq!(*settings.conf.samples_per_pixel(), 200);
+        assert_eq!(*settings.conf.background(), Color::zeros());
+        assert_eq!(settings.world.objects().len(), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_round_trip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        for &chosen in Worlds::value_variants() {
+            let settings = crate::setup(chosen)?;
+            let path = dir.path().join("scene.toml");
+
+            save(&settings, &path)?;
+            let loaded = load(&path)?;
+
+            assert_eq!(
+                export(&loaded, dir.path())?,
+                export(&settings, dir.path())?,
+                "{:?} changed while saving",
+                chosen
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment() -> anyhow::Result<()> {
+        use image::{codecs::hdr::HdrEncoder, Rgb};
+
+        let dir = tempfile::tempdir()?;
+        let file = fs::File::create(dir.path().join("sky.hdr"))?;
+        HdrEncoder::new(file).encode(&[Rgb([0.5, 0.6, 1.0]); 8], 4, 2)?;
+
+        let src = r#"
+            [camera]
+            lookfrom = [0.0, 0.0, 1.0]
+            lookat = [0.0, 0.0, 0.0]
+
+            [config.environment]
+            type = "environment_map"
+            path = "sky.hdr"
+            rotation = 90.0
+        "#;
+        let settings = parse(src, dir.path())?;
+        let environment = export(&settings, dir.path())?.config.environment;
+        assert_eq!(
+            environment,
+            Some(BackgroundDescription::EnvironmentMap {
+                path: "sky.hdr".to_string(),
+                rotation: 90.0,
+                intensity: 1.0,
+            })
+        );
+
+        let missing = src.replace("sky.hdr", "missing.hdr");
+        assert!(parse(&missing, dir.path()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colored_mesh() -> anyhow::Result<()> {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/../ray-tracing/tests/fixtures");
+        let src = r#"
+            [camera]
+            lookfrom = [0.0, 0.0, 1.0]
+            lookat = [0.0, 0.0, 0.0]
+
+            [[objects]]
+            type = "ply"
+            path = "quad_ascii.ply"
+        "#;
+        let settings = parse(src, Path::new(fixtures))?;
+
+        // the whole mesh shares one material reading its vertex colors
+        let desc = export(&settings, Path::new(fixtures))?;
+        assert_eq!(desc.materials.len(), 1);
+        assert_eq!(
+            desc.textures.values().collect::<Vec<_>>(),
+            [&TextureDescription::VertexColor]
+        );
+        match &desc.objects[..] {
+            [ObjectDescription::Mesh {
+                colors: Some(colors),
+                indices,
+                ..
+            }] => {
+                assert_eq!(colors.len(), 4);
+                assert_eq!(indices.len(), 2);
+            }
+            other => panic!("expected a colored mesh, got {:?}", other),
+        }
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("scene.toml");
+        save(&settings, &path)?;
+        assert_eq!(
+            export(&load(&path)?, dir.path())?,
+            export(&settings, dir.path())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance() -> anyhow::Result<()> {
+        let src = r#"
+            [camera]
+            lookfrom = [0.0, 0.0, 1.0]
+            lookat = [0.0, 0.0, 0.0]
+
+            [materials.red]
+            type = "lambertian"
+            albedo = [0.8, 0.1, 0.1]
+
+            [shared.ball]
+            type = "sphere"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = "red"
+
+            [[objects]]
+            type = "instance"
+            matrix = [[2.0, 0.0, 0.0, 1.0], [0.0, 2.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [0.0, 0.0, 0.0, 1.0]]
+            material = "red"
+            object = "ball"
+
+            [[objects]]
+            type = "instance"
+            matrix = [[1.0, 0.0, 0.0, -3.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]
+            object = "ball"
+        "#;
+        let dir = tempfile::tempdir()?;
+        let settings = parse(src, dir.path())?;
+
+        // both instances share the sphere, so it's written out once
+        let desc = export(&settings, dir.path())?;
+        assert_eq!(desc.materials.len(), 1);
+        assert_eq!(desc.shared.len(), 1);
+        assert!(matches!(
+            &desc.objects[..],
+            [
+                ObjectDescription::Instance {
+                    material: Some(_),
+                    object: a,
+                    ..
+                },
+                ObjectDescription::Instance {
+                    material: None,
+                    object: b,
+                    ..
+                },
+            ] if a == b
+        ));
+
+        let path = dir.path().join("scene.toml");
+        save(&settings, &path)?;
+        assert_eq!(export(&load(&path)?, dir.path())?, desc);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_describable() {
+        struct Custom;
+
+        impl ray_tracing::hittable::Hittable for Custom {
+            fn bounding_box(&self, _: f64, _: f64) -> Option<ray_tracing::aabb::Aabb> {
+                None
+            }
+
+            fn hit(
+                &self,
+                _: &ray_tracing::ray::Ray,
+                _: f64,
+                _: f64,
+            ) -> Option<ray_tracing::hittable::HitRecord> {
+                None
+            }
+        }
+
+        let mut settings = crate::setup(Worlds::TwoSpheres).unwrap();
+        settings.world.add(Custom);
+
+        assert!(export(&settings, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let camera = "[camera]\nlookfrom = [0.0, 0.0, 1.0]\nlookat = [0.0, 0.0, 0.0]\n";
+
+        let cases = [
+            // unknown material
+            "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"missing\"\n",
+            // unknown object type
+            "[[objects]]\ntype = \"torus\"\n",
+            // textures referencing each other
+            "[textures.a]\ntype = \"checker\"\neven = \"b\"\nodd = [0.0, 0.0, 0.0]\n\
+             [textures.b]\ntype = \"checker\"\neven = \"a\"\nodd = [0.0, 0.0, 0.0]\n\
+             [materials.m]\ntype = \"lambertian\"\nalbedo = \"a\"\n\
+             [[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"m\"\n",
+            // a transformation flattening the object
+            "[materials.m]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\n\
+             [[objects]]\ntype = \"transform\"\n\
+             matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]\n\
+             object = { type = \"sphere\", center = [0.0, 0.0, 0.0], radius = 1.0, material = \"m\" }\n",
+            // a scale passing zero between the keyframes
+            "[materials.m]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\n\
+             [[objects]]\ntype = \"animated\"\n\
+             keyframes = [{ time = 0.0, scale = [1.0, 1.0, 1.0] }, { time = 1.0, scale = [1.0, -1.0, 1.0] }]\n\
+             object = { type = \"sphere\", center = [0.0, 0.0, 0.0], radius = 1.0, material = \"m\" }\n",
+            // unknown shared object
+            "[[objects]]\ntype = \"instance\"\n\
+             matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]\n\
+             object = \"missing\"\n",
+            // a shared object placing itself
+            "[shared.a]\ntype = \"instance\"\n\
+             matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]\n\
+             object = \"a\"\n\
+             [[objects]]\ntype = \"instance\"\n\
+             matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]\n\
+             object = \"a\"\n",
+        ];
+
+        for case in cases {
+            let src = format!("{}{}", camera, case);
+            assert!(parse(&src, Path::new("")).is_err(), "{} should fail", case);
+        }
+
+        assert!(parse("", Path::new("")).is_err(), "the camera is required");
+        assert!(parse(camera, Path::new("")).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod description;
 pub mod scenes;
 mod setup;
 pub use setup::*;
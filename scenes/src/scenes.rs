@@ -1,24 +1,34 @@
 use std::{cell::RefCell, sync::Arc};
 
 use ray_tracing::{
-    bvh::BvhNode,
+    bvh::LinearBvh,
     hittable::{HittableList, RotateY, Translate},
+    light::{DirectionalLight, Light, PointLight, SpotLight},
     material::{Dielectric, DiffuseLight, Lambertian, Mat, Metal},
     medium,
     objects::{rect, Cube, MovingSphere, Sphere},
+    profile::Sides,
     rand_range,
     ray::{Point, Vec3},
     render::Color,
     texture::{CheckerTexture, ImageTexture, NoiseTexture},
 };
 
+/// The map of the earth, resolved against the workspace so the scenes load
+/// from any working directory.
+const EARTH_MAP: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/earthmap.jpg");
+
 #[derive(Debug, Clone, Copy, clap::ArgEnum)]
 pub enum Worlds {
     RandomScene,
+    /// The random scene under the sky and the sun
+    Daylight,
     TwoPerlinSpheres,
     TwoSpheres,
     Earth,
     SimpleLight,
+    /// Spheres lit by point, spot and directional lights
+    Spotlights,
     CornellBox,
     CornellBoxSmoke,
     FinalScene,
@@ -47,7 +57,7 @@ pub fn final_scene() -> anyhow::Result<HittableList> {
         }
     }
 
-    objects.add(BvhNode::from_hittable_list(&cubes, 0.0, 1.0));
+    objects.add(LinearBvh::new(&cubes, 0.0, 1.0));
 
     let light = DiffuseLight::new([7.0, 7.0, 7.0].into());
     objects.add(rect::XZ::new(light, (123.0, 423.0), (147.0, 412.0), 554.0));
@@ -88,7 +98,7 @@ pub fn final_scene() -> anyhow::Result<HittableList> {
         &[1.0, 1.0, 1.0].into(),
     ));
 
-    let emat = Lambertian::with_texture(ImageTexture::new("assets/earthmap.jpg")?);
+    let emat = Lambertian::with_texture(ImageTexture::new(EARTH_MAP)?);
     objects.add(Sphere::new(
         [400.0, 200.0, 400.0].into(),
         100.0,
@@ -112,7 +122,7 @@ pub fn final_scene() -> anyhow::Result<HittableList> {
     }
 
     objects.add(Translate::new(
-        RotateY::new(BvhNode::from_hittable_list(&cubes, 0.0, 1.0), 15.0),
+        RotateY::new(LinearBvh::new(&cubes, 0.0, 1.0), 15.0),
         [-100.0, 270.0, 395.0].into(),
     ));
 
@@ -126,7 +136,9 @@ pub fn cornell_box() -> HittableList {
     let red = Lambertian::new([0.65, 0.05, 0.05].into());
     let white = Lambertian::new([0.73, 0.73, 0.73].into());
     let green = Lambertian::new([0.12, 0.45, 0.15].into());
-    let light = DiffuseLight::new([15.0, 15.0, 15.0].into());
+    // only lights the box below it
+    let mut light = DiffuseLight::new([15.0, 15.0, 15.0].into());
+    light.set_sides(Sides::Back);
 
     // Walls
     for (k, mp) in [(555.0, green), (0.0, red)] {
@@ -170,7 +182,9 @@ pub fn cornell_box_smoke() -> HittableList {
     let red = Lambertian::new([0.65, 0.05, 0.05].into());
     let white = Lambertian::new([0.73, 0.73, 0.73].into());
     let green = Lambertian::new([0.12, 0.45, 0.15].into());
-    let light = DiffuseLight::new([15.0, 15.0, 15.0].into());
+    // only lights the box below it
+    let mut light = DiffuseLight::new([15.0, 15.0, 15.0].into());
+    light.set_sides(Sides::Back);
 
     // Walls
     for (k, mp) in [(555.0, green), (0.0, red)] {
@@ -239,10 +253,63 @@ pub fn simple_light() -> HittableList {
     world
 }
 
+pub fn spotlights() -> (HittableList, Vec<Arc<dyn Light>>) {
+    let mut world = HittableList::new();
+
+    let checker = CheckerTexture::with_color(Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+    world.add(Sphere::new(
+        [0.0, -1000.0, 0.0].into(),
+        1000.0,
+        Arc::new(Lambertian::with_texture(checker)),
+    ));
+
+    let spheres: [(Point, Mat); 3] = [
+        (
+            [-4.0, 1.0, 0.0].into(),
+            Arc::new(Lambertian::new([0.7, 0.7, 0.7].into())),
+        ),
+        ([0.0, 1.0, 0.0].into(), Arc::new(Dielectric::new(1.5))),
+        (
+            [4.0, 1.0, 0.0].into(),
+            Arc::new(Metal::new([0.7, 0.6, 0.5].into(), 0.1)),
+        ),
+    ];
+    for (center, mat) in spheres {
+        world.add(Sphere::new(center, 1.0, mat));
+    }
+
+    let lights: Vec<Arc<dyn Light>> = vec![
+        Arc::new(SpotLight::new(
+            [-4.0, 6.0, 2.0].into(),
+            [0.0, -6.0, -2.0].into(),
+            [40.0, 8.0, 8.0].into(),
+            25.0,
+            15.0,
+        )),
+        Arc::new(SpotLight::new(
+            [4.0, 6.0, 2.0].into(),
+            [0.0, -6.0, -2.0].into(),
+            [8.0, 8.0, 40.0].into(),
+            25.0,
+            15.0,
+        )),
+        Arc::new(PointLight::new(
+            [2.0, 3.0, 4.0].into(),
+            [20.0, 20.0, 16.0].into(),
+        )),
+        Arc::new(DirectionalLight::new(
+            [1.0, -2.0, -1.0].into(),
+            [0.05, 0.05, 0.1].into(),
+        )),
+    ];
+
+    (world, lights)
+}
+
 pub fn earth() -> anyhow::Result<HittableList> {
     let mut world = HittableList::new();
 
-    let earth_texture = ImageTexture::new("assets/earthmap.jpg")?;
+    let earth_texture = ImageTexture::new(EARTH_MAP)?;
     let earth_surface = Lambertian::with_texture(earth_texture);
     let globe = Sphere::new([0.0, 0.0, 0.0].into(), 2.0, Arc::new(earth_surface));
 
@@ -1,10 +1,16 @@
-use ray_tracing::{camera::Camera, hittable::HittableList, ray::Point, render::Color, Config};
+use std::sync::Arc;
+
+use ray_tracing::{
+    camera::Camera, hittable::HittableList, ray::Point, render::Color, sky::Sky, Config,
+};
 
 use crate::scenes::{self, Worlds};
 
 pub struct WorldSettings {
     pub conf: Config,
     pub world: HittableList,
+    /// The lights of the world sampled directly by the renderer.
+    pub lights: HittableList,
     pub cam: Camera,
 }
 
@@ -27,6 +33,11 @@ pub fn setup(chosen: Worlds) -> anyhow::Result<WorldSettings> {
             aperture = 0.1;
             scenes::random_scene()
         }
+        Worlds::Daylight => {
+            aperture = 0.1;
+            world_conf.set_environment(Some(Arc::new(Sky::new(35.0, 60.0, 3.0))));
+            scenes::random_scene()
+        }
         Worlds::TwoSpheres => scenes::two_spheres(),
         Worlds::TwoPerlinSpheres => scenes::two_perlin_spheres(),
         Worlds::Earth => scenes::earth()?,
@@ -37,6 +48,17 @@ pub fn setup(chosen: Worlds) -> anyhow::Result<WorldSettings> {
             world_conf.set_background(Color::zeros());
             scenes::simple_light()
         }
+        Worlds::Spotlights => {
+            lookfrom = [12.0, 4.0, 10.0].into();
+            lookat = [0.0, 1.0, 0.0].into();
+            vfov = 30.0;
+            world_conf.set_background(Color::zeros());
+            let (world, lights) = scenes::spotlights();
+            for light in lights {
+                world_conf.add_light(light);
+            }
+            world
+        }
         Worlds::CornellBox => {
             world_conf.set_aspect_ratio(1.0);
             world_conf.set_image_width(600);
@@ -84,6 +106,7 @@ pub fn setup(chosen: Worlds) -> anyhow::Result<WorldSettings> {
 
     Ok(WorldSettings {
         conf: world_conf,
+        lights: world.lights(),
         world,
         cam,
     })
@@ -0,0 +1,152 @@
+//! Renders every built-in world at a tiny resolution with a fixed seed and
+//! compares the result to the reference images in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to (re)create the reference images after an
+//! intended change of the output.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use image::RgbImage;
+use ray_tracing::integrator::PathTracer;
+use scenes::scenes::Worlds;
+
+const WIDTH: usize = 32;
+const SAMPLES_PER_PIXEL: usize = 16;
+const SEED: u64 = 0x5eed;
+
+/// The minimal peak signal to noise ratio in dB, identical images have an
+/// infinite one. Leaves room for floating point differences between platforms.
+const MIN_PSNR: f64 = 30.0;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn render(chosen: Worlds) -> RgbImage {
+    ray_tracing::seed_rng(SEED);
+    let mut settings = scenes::setup(chosen).expect("unable to setup the scene");
+
+    let conf = &mut settings.conf;
+    conf.set_image_width(WIDTH);
+    conf.set_samples_per_pixel(SAMPLES_PER_PIXEL);
+    conf.set_seed(SEED);
+
+    let data = ray_tracing::run_hidden(
+        &settings.world,
+        &settings.lights,
+        &PathTracer::default(),
+        conf,
+        &settings.cam,
+    );
+
+    let mut img = RgbImage::new(conf.image_width() as u32, conf.image_height() as u32);
+    for (pixel, color) in img.pixels_mut().zip(data) {
+        pixel.0 = [color.x() as u8, color.y() as u8, color.z() as u8];
+    }
+    img
+}
+
+/// Returns the root mean square error and the peak signal to noise ratio.
+fn compare(a: &RgbImage, b: &RgbImage) -> (f64, f64) {
+    let squared: f64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+
+    let rmse = (squared / a.as_raw().len() as f64).sqrt();
+    let psnr = 20.0 * f64::log10(255.0 / rmse);
+    (rmse, psnr)
+}
+
+/// Writes the rendered, the reference and an amplified difference image.
+fn write_diff(name: &str, actual: &RgbImage, expected: &RgbImage) -> PathBuf {
+    let dir = env::temp_dir().join(format!("golden-{}", name));
+    std::fs::create_dir_all(&dir).expect("unable to create the diff directory");
+
+    let mut diff = actual.clone();
+    for (d, e) in diff.pixels_mut().zip(expected.pixels()) {
+        for (d, &e) in d.0.iter_mut().zip(e.0.iter()) {
+            *d = ((*d as i32 - e as i32).abs() * 4).min(255) as u8;
+        }
+    }
+
+    for (file, img) in [
+        ("actual.png", actual),
+        ("expected.png", expected),
+        ("diff.png", &diff),
+    ] {
+        img.save(dir.join(file))
+            .expect("unable to write the diff images");
+    }
+
+    dir
+}
+
+fn check(name: &str, chosen: Worlds) {
+    let actual = render(chosen);
+    let path = golden_dir().join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&path).expect("unable to write the reference");
+        return;
+    }
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|err| {
+            panic!(
+                "unable to read {}: {}, run with UPDATE_GOLDEN=1 to create it",
+                path.display(),
+                err
+            )
+        })
+        .to_rgb8();
+
+    assert_eq!(
+        actual.dimensions(),
+        expected.dimensions(),
+        "{} has the wrong size",
+        name
+    );
+
+    let (rmse, psnr) = compare(&actual, &expected);
+    if psnr < MIN_PSNR {
+        let dir = write_diff(name, &actual, &expected);
+        panic!(
+            "{} differs from the reference (rmse {:.3}, psnr {:.2} dB < {} dB), see {}",
+            name,
+            rmse,
+            psnr,
+            MIN_PSNR,
+            dir.display()
+        );
+    }
+}
+
+macro_rules! golden {
+    ($($name:ident => $world:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check(stringify!($name), $world);
+            }
+        )*
+    };
+}
+
+golden! {
+    random_scene => Worlds::RandomScene,
+    daylight => Worlds::Daylight,
+    two_perlin_spheres => Worlds::TwoPerlinSpheres,
+    two_spheres => Worlds::TwoSpheres,
+    earth => Worlds::Earth,
+    simple_light => Worlds::SimpleLight,
+    spotlights => Worlds::Spotlights,
+    cornell_box => Worlds::CornellBox,
+    cornell_box_smoke => Worlds::CornellBoxSmoke,
+    final_scene => Worlds::FinalScene,
+}
@@ -0,0 +1,83 @@
+//! Checks that sampling the lights directly reduces the noise of the cornell
+//! box without changing its brightness.
+
+use ray_tracing::{hittable::HittableList, integrator::PathTracer, render::Color};
+use scenes::{scenes::Worlds, WorldSettings};
+
+const WIDTH: usize = 32;
+const SAMPLES_PER_PIXEL: usize = 16;
+
+/// Renders without gamma correction, the clamped pixels are linear in the
+/// incoming light.
+fn render(
+    settings: &mut WorldSettings,
+    lights: &HittableList,
+    samples_per_pixel: usize,
+    seed: u64,
+) -> Vec<Color> {
+    let conf = &mut settings.conf;
+    conf.set_image_width(WIDTH);
+    conf.set_samples_per_pixel(samples_per_pixel);
+    conf.set_gamma(1.0);
+    conf.set_seed(seed);
+
+    ray_tracing::run_hidden(
+        &settings.world,
+        lights,
+        &PathTracer::default(),
+        conf,
+        &settings.cam,
+    )
+}
+
+/// The mean squared difference of two renders, which is twice the variance
+/// of a single one.
+fn mean_squared_difference(a: &[Color], b: &[Color]) -> f64 {
+    let sum: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| (*a - *b).length_squared())
+        .sum();
+    sum / a.len() as f64
+}
+
+fn mean(a: &[Color]) -> f64 {
+    a.iter().map(|c| c.x() + c.y() + c.z()).sum::<f64>() / (3 * a.len()) as f64
+}
+
+#[test]
+fn test_cornell_box_noise() {
+    let mut settings = scenes::setup(Worlds::CornellBox).expect("unable to setup the scene");
+    let lights = std::mem::take(&mut settings.lights);
+    assert_eq!(lights.objects().len(), 1, "the ceiling light is not found");
+
+    let none = HittableList::new();
+    let plain = [
+        render(&mut settings, &none, SAMPLES_PER_PIXEL, 1),
+        render(&mut settings, &none, SAMPLES_PER_PIXEL, 2),
+    ];
+    let nee = [
+        render(&mut settings, &lights, SAMPLES_PER_PIXEL, 1),
+        render(&mut settings, &lights, SAMPLES_PER_PIXEL, 2),
+    ];
+
+    let plain_noise = mean_squared_difference(&plain[0], &plain[1]);
+    let nee_noise = mean_squared_difference(&nee[0], &nee[1]);
+    assert!(
+        nee_noise < 0.5 * plain_noise,
+        "light sampling has a noise of {} against {} without",
+        nee_noise,
+        plain_noise
+    );
+
+    // the noisy plain render loses more light to the clamping, so the
+    // reference gets more samples
+    let reference = render(&mut settings, &none, 16 * SAMPLES_PER_PIXEL, 3);
+    let (plain_mean, nee_mean) = (mean(&reference), mean(&nee[0]));
+    assert!(
+        (plain_mean - nee_mean).abs() < 0.05 * plain_mean,
+        "light sampling changed the brightness from {} to {}",
+        plain_mean,
+        nee_mean
+    );
+}
@@ -1,5 +1,6 @@
 use std::{
     panic,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -8,8 +9,14 @@ use std::{
     time::Duration,
 };
 
+use anyhow::Context;
+use clap::Parser;
 use indicatif::{MultiProgress, ProgressBar, ProgressIterator, ProgressStyle};
 use ray_tracing::{
+    environment::EnvironmentMap,
+    integrator::{
+        AmbientOcclusion, DebugView, DirectLighting, Integrator, Mis, PathTracer, ROULETTE_DEPTH,
+    },
     render::{self, Color, Image},
     Config,
 };
@@ -18,7 +25,13 @@ use scenes::{scenes::Worlds, WorldSettings};
 pub const REPETITION: usize = 1;
 
 pub fn run(
-    WorldSettings { conf, world, cam }: &WorldSettings,
+    WorldSettings {
+        conf,
+        world,
+        lights,
+        cam,
+    }: &WorldSettings,
+    integrator: &dyn Integrator,
     pb_run: ProgressBar,
     pb_int: ProgressBar,
 ) -> anyhow::Result<Vec<Color>> {
@@ -28,7 +41,7 @@ pub fn run(
     // SAFETY: the unwrap is safe here as we know
     // that there allways will be a result.
     let mut res = (0..REPETITION)
-        .map(|_| ray_tracing::run(world, &conf, pb_int.clone(), &cam))
+        .map(|_| ray_tracing::run(world, lights, integrator, conf, pb_int.clone(), cam))
         .progress_with(pb_run)
         .reduce(|mut acc, v| {
             for (a, b) in acc.iter_mut().zip(v.iter()) {
@@ -46,20 +59,234 @@ pub fn run(
     Ok(res)
 }
 
-
-#[derive(clap::Parser)]
+#[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    #[clap(arg_enum)]
-    scenes: Worlds
+    #[clap(arg_enum, required_unless_present = "file")]
+    scenes: Option<Worlds>,
+
+    /// Render the scene described in the given toml file instead
+    #[clap(short, long, conflicts_with = "scenes")]
+    file: Option<PathBuf>,
+
+    /// Write the scene to the given toml file instead of rendering it
+    #[clap(long)]
+    export: Option<PathBuf>,
+
+    /// The path of the rendered image
+    #[clap(short, long, default_value = "main")]
+    output: PathBuf,
+
+    /// The format of the rendered image, taken from the output path if not given
+    #[clap(long, arg_enum)]
+    format: Option<Format>,
+
+    /// The width of the image in pixels
+    #[clap(short, long)]
+    width: Option<usize>,
+
+    /// The aspect ratio of the image (width / height)
+    #[clap(short, long)]
+    aspect_ratio: Option<f64>,
+
+    /// The number of samples per pixel
+    #[clap(short, long)]
+    samples: Option<usize>,
+
+    /// The maximum number of bounces of a ray
+    #[clap(short = 'd', long)]
+    max_depth: Option<usize>,
+
+    /// The gamma used to correct the image
+    #[clap(short, long)]
+    gamma: Option<f64>,
+
+    /// The background color as r,g,b
+    #[clap(short, long, parse(try_from_str = parse_color))]
+    background: Option<Color>,
+
+    /// An equirectangular Radiance .hdr or OpenEXR .exr image lighting the
+    /// scene instead of the background color
+    #[clap(long)]
+    environment: Option<PathBuf>,
+
+    /// The rotation of the environment around the y axis in degrees
+    #[clap(long, requires = "environment", default_value = "0")]
+    environment_rotation: f64,
+
+    /// The factor the environment is scaled by
+    #[clap(long, requires = "environment", default_value = "1")]
+    environment_intensity: f64,
+
+    /// The number of threads used for rendering, defaults to the number of cores
+    #[clap(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// The seed of the random number generator
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// The algorithm used to gather the light
+    #[clap(short, long, arg_enum, default_value = "path")]
+    integrator: IntegratorKind,
+
+    /// The number of bounces before the path tracer ends paths at random
+    #[clap(long)]
+    roulette_depth: Option<usize>,
+
+    /// How the path and the direct lighting integrators weight sampling the
+    /// lights against sampling the materials
+    #[clap(long, arg_enum, default_value = "balance")]
+    mis: MisKind,
+
+    /// The distance within which objects occlude each other for the ao integrator
+    #[clap(long)]
+    ao_distance: Option<f64>,
 }
 
-pub fn create_image() -> anyhow::Result<(Config, Vec<Color>)> {
-    use clap::Parser;
-    let args = Args::parse();
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+enum MisKind {
+    Balance,
+    Power,
+}
+
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+enum IntegratorKind {
+    /// The full path tracer
+    Path,
+    /// Only the light arriving directly from the lights
+    Direct,
+    /// Ambient occlusion
+    Ao,
+    /// The normals of the first hit
+    Normals,
+    /// The texture coordinates of the first hit
+    Uv,
+    /// Whether the first hit is on the outside of an object
+    FrontFace,
+}
+
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+enum Format {
+    Png,
+    Ppm,
+}
+
+impl Args {
+    /// The requested file format, falling back to the extension of the output.
+    fn file_format(&self) -> render::FileFormat {
+        let format = self
+            .format
+            .or_else(|| match self.output.extension()?.to_str()? {
+                "ppm" => Some(Format::Ppm),
+                "png" => Some(Format::Png),
+                _ => None,
+            });
+
+        match format {
+            Some(Format::Ppm) => render::FileFormat::PPM,
+            Some(Format::Png) | None => render::FileFormat::PNG,
+        }
+    }
+
+    fn mis(&self) -> Mis {
+        match self.mis {
+            MisKind::Balance => Mis::Balance,
+            MisKind::Power => Mis::Power,
+        }
+    }
+
+    fn integrator(&self) -> Box<dyn Integrator> {
+        match self.integrator {
+            IntegratorKind::Path => {
+                let depth = self.roulette_depth.unwrap_or(ROULETTE_DEPTH);
+                Box::new(PathTracer::with_mis(depth, self.mis()))
+            }
+            IntegratorKind::Direct => Box::new(DirectLighting::new(self.mis())),
+            IntegratorKind::Ao => Box::new(match self.ao_distance {
+                Some(distance) => AmbientOcclusion::new(distance),
+                None => AmbientOcclusion::default(),
+            }),
+            IntegratorKind::Normals => Box::new(DebugView::Normals),
+            IntegratorKind::Uv => Box::new(DebugView::Uv),
+            IntegratorKind::FrontFace => Box::new(DebugView::FrontFace),
+        }
+    }
 
-    // setup render
-    let settings = scenes::setup(args.scenes)?;
+    /// Overrides the settings of the scene with the given options.
+    fn apply(&self, settings: &mut WorldSettings) -> anyhow::Result<()> {
+        let conf = &mut settings.conf;
+
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            conf.set_aspect_ratio(aspect_ratio);
+            settings.cam = settings.cam.describe().build(aspect_ratio);
+        }
+        if let Some(width) = self.width {
+            conf.set_image_width(width);
+        }
+        if let Some(samples) = self.samples {
+            conf.set_samples_per_pixel(samples);
+        }
+        if let Some(max_depth) = self.max_depth {
+            conf.set_max_depth(max_depth);
+        }
+        if let Some(gamma) = self.gamma {
+            conf.set_gamma(gamma);
+        }
+        if let Some(background) = self.background {
+            conf.set_background(background);
+        }
+        if let Some(seed) = self.seed {
+            conf.set_seed(seed);
+        }
+        if let Some(path) = &self.environment {
+            let mut env = EnvironmentMap::load(path)
+                .with_context(|| format!("unable to load environment map {}", path.display()))?;
+            env.set_rotation(self.environment_rotation);
+            env.set_intensity(self.environment_intensity);
+            conf.set_environment(Some(Arc::new(env)));
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    let vals = s
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match vals[..] {
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => Err(format!(
+            "expected three values (r,g,b) but got {}",
+            vals.len()
+        )),
+    }
+}
+
+fn setup(args: &Args) -> anyhow::Result<WorldSettings> {
+    // The seed also drives the random parts of the scene creation.
+    if let Some(seed) = args.seed {
+        ray_tracing::seed_rng(seed);
+    }
+
+    let mut settings = match (&args.file, args.scenes) {
+        (Some(file), _) => scenes::description::load(file)?,
+        (None, Some(chosen)) => scenes::setup(chosen)?,
+        (None, None) => unreachable!("clap requires either a scene or a file"),
+    };
+
+    args.apply(&mut settings)?;
+
+    Ok(settings)
+}
+
+pub fn create_image(
+    settings: WorldSettings,
+    integrator: Box<dyn Integrator>,
+) -> anyhow::Result<(Config, Vec<Color>)> {
     let conf = settings.conf.clone();
 
     // ProgressBar
@@ -107,7 +334,7 @@ pub fn create_image() -> anyhow::Result<(Config, Vec<Color>)> {
     let mp_handler = thread::spawn(move || mp.join());
 
     let data = thread::spawn(move || {
-        let res = run(&settings, pb_run.clone(), pb_curr.clone());
+        let res = run(&settings, &*integrator, pb_run.clone(), pb_curr.clone());
 
         for pb in [pb_curr, pb_run] {
             pb.finish();
@@ -139,16 +366,32 @@ pub fn create_image() -> anyhow::Result<(Config, Vec<Color>)> {
 }
 
 fn main() {
-    // Image
-    let path = "main";
+    let args = Args::parse();
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("unable to setup the thread pool");
+    }
+
+    let settings = setup(&args).expect("unable to setup the scene");
+
+    if let Some(export) = &args.export {
+        scenes::description::save(&settings, export).expect("unable to export the scene");
+        println!("Exported the scene to {}", export.display());
+        return;
+    }
 
     println!("Running");
 
-    let (conf, data) = create_image().expect("unable to get the data, due to some error");
+    let (conf, data) = create_image(settings, args.integrator())
+        .expect("unable to get the data, due to some error");
 
     println!("Writing data");
     let img = Image::new(&data, conf.image_height(), conf.image_width());
 
-    render::save(img, path, render::FileFormat::PNG).expect("Something went terribly wrong here");
+    render::save(img, &args.output, args.file_format())
+        .expect("Something went terribly wrong here");
     println!("Done");
 }